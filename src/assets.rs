@@ -1,3 +1,11 @@
+use crate::boss::{BossDef, BOSSES_FILE};
+use crate::data::GameData;
+use crate::difficulty::{self, DIFFICULTIES_FILE};
+use crate::enemy::{self, FirePattern, ENEMIES_FILE};
+use crate::level::{Level, LEVELS_DIR, LEVEL_INDEX};
+use crate::pattern::{Pattern, PATTERNS_DIR, PATTERN_INDEX};
+use crate::weapon::{WeaponDef, WEAPON_FILE};
+use std::{collections::HashMap, rc::Rc};
 use macroquad::{
     audio::{load_sound, Sound}, 
     prelude::*, 
@@ -9,13 +17,15 @@ pub struct Resources {
     pub ship_texture: Texture2D,
     pub bullet_texture: Texture2D,
     pub explosion_texture: Texture2D,
-    pub enemy_small_texture: Texture2D,
-    pub enemy_med_texture: Texture2D,
-    pub enemy_big_texture: Texture2D,
+    // Enemy and boss part textures keyed by file name
+    pub enemy_textures: HashMap<String, Texture2D>,
     pub theme_music: Sound,
     pub sound_explosion: Sound,
     pub sound_laser: Sound,
     pub ui_skin: Skin,
+    // The skin's font, for text drawn outside the UI
+    pub font: Font,
+    pub data: Rc<GameData>,
 }
 
 impl Resources {
@@ -26,12 +36,102 @@ impl Resources {
         bullet_texture.set_filter(FilterMode::Nearest);
         let explosion_texture: Texture2D = load_texture("explosion.png").await?;
         explosion_texture.set_filter(FilterMode::Nearest);
-        let enemy_small_texture: Texture2D = load_texture("enemy-small.png").await?;
-        enemy_small_texture.set_filter(FilterMode::Nearest);
-        let enemy_med_texture: Texture2D = load_texture("enemy-medium.png").await?;
-        enemy_med_texture.set_filter(FilterMode::Nearest);
-        let enemy_big_texture: Texture2D = load_texture("enemy-big.png").await?;
-        enemy_big_texture.set_filter(FilterMode::Nearest);
+
+        let enemies = enemy::parse_archetypes(&load_string(ENEMIES_FILE).await?).map_err(|err| {
+            error!("{}: {}", ENEMIES_FILE, err);
+            macroquad::Error::UnknownError("invalid enemy archetypes")
+        })?;
+
+        let pattern_files: Vec<String> = ron::from_str(&load_string(PATTERN_INDEX).await?).map_err(|err| {
+            error!("{}: {}", PATTERN_INDEX, err);
+            macroquad::Error::UnknownError("invalid pattern index")
+        })?;
+        let mut patterns = HashMap::new();
+        for file in pattern_files {
+            let path = format!("{}/{}", PATTERNS_DIR, file);
+            let pattern = Pattern::parse(&load_string(&path).await?).map_err(|err| {
+                error!("{}: {}", path, err);
+                macroquad::Error::UnknownError("invalid bullet pattern")
+            })?;
+            patterns.insert(pattern.name.clone(), pattern);
+        }
+        for archetype in &enemies {
+            if let Some(FirePattern::Script(name)) = archetype.fire.as_ref().map(|fire| &fire.pattern) {
+                if !patterns.contains_key(name) {
+                    error!("Enemy {} uses unknown pattern {}", archetype.name, name);
+                    return Err(macroquad::Error::UnknownError("unknown bullet pattern"));
+                }
+            }
+        }
+
+        let bosses = BossDef::parse_list(&load_string(BOSSES_FILE).await?).map_err(|err| {
+            error!("{}: {}", BOSSES_FILE, err);
+            macroquad::Error::UnknownError("invalid bosses")
+        })?;
+        for boss in &bosses {
+            if let Err(err) = boss.validate(|name| patterns.contains_key(name)) {
+                error!("{}: {}", BOSSES_FILE, err);
+                return Err(macroquad::Error::UnknownError("invalid boss"));
+            }
+        }
+
+        let weapon = WeaponDef::parse(&load_string(WEAPON_FILE).await?).map_err(|err| {
+            error!("{}: {}", WEAPON_FILE, err);
+            macroquad::Error::UnknownError("invalid weapon")
+        })?;
+        if weapon.tiers.is_empty() {
+            error!("{}: no tiers", WEAPON_FILE);
+            return Err(macroquad::Error::UnknownError("invalid weapon"));
+        }
+
+        let difficulties = difficulty::parse_difficulties(&load_string(DIFFICULTIES_FILE).await?).map_err(|err| {
+            error!("{}: {}", DIFFICULTIES_FILE, err);
+            macroquad::Error::UnknownError("invalid difficulties")
+        })?;
+        if difficulties.is_empty() {
+            error!("{}: no difficulties", DIFFICULTIES_FILE);
+            return Err(macroquad::Error::UnknownError("invalid difficulties"));
+        }
+
+        let level_files: Vec<String> = ron::from_str(&load_string(LEVEL_INDEX).await?).map_err(|err| {
+            error!("{}: {}", LEVEL_INDEX, err);
+            macroquad::Error::UnknownError("invalid level index")
+        })?;
+        let mut levels = vec![];
+        for file in level_files {
+            let path = format!("{}/{}", LEVELS_DIR, file);
+            let level = Level::parse(&load_string(&path).await?).map_err(|err| {
+                error!("{}: {}", path, err);
+                macroquad::Error::UnknownError("invalid level")
+            })?;
+            for wave in &level.waves {
+                if !enemies.iter().any(|archetype| archetype.name == wave.enemy) {
+                    error!("{} uses unknown enemy {}", path, wave.enemy);
+                    return Err(macroquad::Error::UnknownError("unknown enemy archetype"));
+                }
+            }
+            if let Some(name) = &level.boss {
+                if !bosses.iter().any(|boss| &boss.name == name) {
+                    error!("{} uses unknown boss {}", path, name);
+                    return Err(macroquad::Error::UnknownError("unknown boss"));
+                }
+            }
+            levels.push(level);
+        }
+
+        let mut enemy_textures = HashMap::new();
+        let mut texture_files = enemies
+            .iter()
+            .map(|archetype| archetype.texture.clone())
+            .chain(bosses.iter().flat_map(|boss| boss.parts.iter().map(|part| part.texture.clone())))
+            .collect::<Vec<_>>();
+        texture_files.sort();
+        texture_files.dedup();
+        for file in texture_files {
+            let texture: Texture2D = load_texture(&file).await?;
+            texture.set_filter(FilterMode::Nearest);
+            enemy_textures.insert(file, texture);
+        }
         build_textures_atlas();
 
         let theme_music = load_sound("8bit-spaceshooter.ogg").await?;
@@ -42,6 +142,7 @@ impl Resources {
         let button_background = load_image("button_background.png").await?;
         let button_clicked_background = load_image("button_clicked_background.png").await?;
         let font = load_file("atari_games.ttf").await?;
+        let ui_font = load_ttf_font_from_bytes(&font)?;
 
         let window_style = root_ui()
             .style_builder()
@@ -76,13 +177,23 @@ impl Resources {
             ship_texture,
             bullet_texture,
             explosion_texture,
-            enemy_small_texture,
-            enemy_med_texture,
-            enemy_big_texture,
+            enemy_textures,
             theme_music,
             sound_explosion,
             sound_laser,
             ui_skin,
+            font: ui_font,
+            data: Rc::new(GameData {
+                enemies,
+                patterns: patterns
+                    .into_iter()
+                    .map(|(name, pattern)| (name, Rc::new(pattern)))
+                    .collect(),
+                levels,
+                bosses,
+                weapon,
+                difficulties,
+            }),
         })
     }
     pub async fn load() -> Result<(), macroquad::Error> {
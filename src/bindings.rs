@@ -0,0 +1,359 @@
+use crate::gamepad::MenuInput;
+use crate::player::MAX_PLAYERS;
+use crate::storage::{self, Storage};
+use crate::touch::{self, TouchControls};
+use macroquad::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::BTreeMap, io};
+
+pub const BINDINGS_FILE: &str = "bindings.ron";
+// Bump when the layout of `Bindings` changes
+const BINDINGS_VERSION: u32 = 1;
+
+// Everything a key can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    Bomb,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Fire,
+        Action::Bomb,
+        Action::Pause,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Fire => "Fire",
+            Action::Bomb => "Bomb",
+            Action::Pause => "Pause",
+        }
+    }
+}
+
+// Keys that can be bound, which are also the names the bindings file accepts.
+// Escape is left out: it always pauses, backs out of menus and cancels
+// rebinding.
+const BINDABLE: [KeyCode; 94] = [
+    KeyCode::Space, KeyCode::Apostrophe, KeyCode::Comma, KeyCode::Minus, KeyCode::Period,
+    KeyCode::Slash, KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Semicolon, KeyCode::Equal, KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D,
+    KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K,
+    KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+    KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y,
+    KeyCode::Z, KeyCode::LeftBracket, KeyCode::Backslash, KeyCode::RightBracket,
+    KeyCode::GraveAccent, KeyCode::Enter, KeyCode::Tab, KeyCode::Backspace,
+    KeyCode::Insert, KeyCode::Delete, KeyCode::Right, KeyCode::Left, KeyCode::Down,
+    KeyCode::Up, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Home, KeyCode::End,
+    KeyCode::Pause, KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5,
+    KeyCode::F6, KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11,
+    KeyCode::F12, KeyCode::Kp0, KeyCode::Kp1, KeyCode::Kp2, KeyCode::Kp3, KeyCode::Kp4,
+    KeyCode::Kp5, KeyCode::Kp6, KeyCode::Kp7, KeyCode::Kp8, KeyCode::Kp9,
+    KeyCode::KpDecimal, KeyCode::KpDivide, KeyCode::KpMultiply, KeyCode::KpSubtract,
+    KeyCode::KpAdd, KeyCode::KpEnter, KeyCode::LeftShift, KeyCode::LeftControl,
+    KeyCode::RightShift, KeyCode::RightControl,
+];
+
+// A keyboard key, written to the bindings file by name
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key(pub KeyCode);
+
+impl Key {
+    pub fn name(self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        BINDABLE
+            .iter()
+            .map(|key| Key(*key))
+            .find(|key| key.name() == name)
+            .ok_or_else(|| de::Error::custom(format!("unknown key {}", name)))
+    }
+}
+
+// Which key does what, for every player
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bindings {
+    pub version: u32,
+    // One map per player; actions missing from it are unbound
+    pub players: Vec<BTreeMap<Action, Key>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let first = [
+            (Action::MoveLeft, KeyCode::Left),
+            (Action::MoveRight, KeyCode::Right),
+            (Action::MoveUp, KeyCode::Up),
+            (Action::MoveDown, KeyCode::Down),
+            (Action::Fire, KeyCode::Space),
+            (Action::Bomb, KeyCode::X),
+            (Action::Pause, KeyCode::P),
+        ];
+        // The second player shares the left side of the keyboard and leaves
+        // pausing to the first
+        let second = [
+            (Action::MoveLeft, KeyCode::A),
+            (Action::MoveRight, KeyCode::D),
+            (Action::MoveUp, KeyCode::W),
+            (Action::MoveDown, KeyCode::S),
+            (Action::Fire, KeyCode::F),
+            (Action::Bomb, KeyCode::G),
+        ];
+        Bindings {
+            version: BINDINGS_VERSION,
+            players: vec![
+                first.into_iter().map(|(action, key)| (action, Key(key))).collect(),
+                second.into_iter().map(|(action, key)| (action, Key(key))).collect(),
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    pub fn load(storage: &dyn Storage) -> Bindings {
+        storage::load_or_back_up(storage, BINDINGS_FILE, Bindings::parse).unwrap_or_default()
+    }
+
+    fn parse(text: &str) -> Result<Bindings, String> {
+        let bindings: Bindings = ron::from_str(text).map_err(|err| err.to_string())?;
+        if bindings.version != BINDINGS_VERSION {
+            return Err(format!("unknown bindings version {}", bindings.version));
+        }
+        Ok(bindings.filled())
+    }
+
+    pub fn save(&self, storage: &dyn Storage) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        storage.write(BINDINGS_FILE, text.as_bytes())
+    }
+
+    // Files written for fewer players get the defaults for the rest
+    fn filled(mut self) -> Bindings {
+        let defaults = Bindings::default();
+        self.players.truncate(MAX_PLAYERS);
+        self.players
+            .extend(defaults.players.into_iter().skip(self.players.len()));
+        self
+    }
+
+    pub fn key(&self, player: usize, action: Action) -> Option<Key> {
+        self.players.get(player)?.get(&action).copied()
+    }
+
+    pub fn is_down(&self, player: usize, action: Action) -> bool {
+        self.key(player, action).is_some_and(|key| is_key_down(key.0))
+    }
+
+    pub fn is_pressed(&self, player: usize, action: Action) -> bool {
+        self.key(player, action).is_some_and(|key| is_key_pressed(key.0))
+    }
+
+    // Pressed by any player this frame
+    pub fn any_pressed(&self, action: Action) -> bool {
+        (0..self.players.len()).any(|player| self.is_pressed(player, action))
+    }
+
+    // "[Space]" for the HUD and menus
+    pub fn key_label(&self, player: usize, action: Action) -> String {
+        match self.key(player, action) {
+            Some(key) => format!("[{}]", key.name()),
+            None => "(unbound)".to_string(),
+        }
+    }
+
+    // Another player or action already using `key`, if any
+    pub fn conflict(&self, player: usize, action: Action, key: Key) -> Option<(usize, Action)> {
+        self.players.iter().enumerate().find_map(|(other_player, keys)| {
+            keys.iter()
+                .find(|(other_action, other_key)| {
+                    **other_key == key && (other_player, **other_action) != (player, action)
+                })
+                .map(|(other_action, _)| (other_player, *other_action))
+        })
+    }
+}
+
+// Cursor and state of the rebinding screen
+#[derive(Default)]
+pub struct BindingsMenu {
+    // Row the cursor is on
+    pub action: usize,
+    pub player: usize,
+    // Waiting for the key to bind to the selected action
+    pub capturing: bool,
+    // Feedback from the last attempt, like a conflict
+    pub message: Option<String>,
+}
+
+impl BindingsMenu {
+    // Handle this frame's input; returns true when the player leaves the screen.
+    // The screen is driven by fixed keys so it stays usable whatever is bound.
+    pub fn update(
+        &mut self,
+        bindings: &mut Bindings,
+        storage: &dyn Storage,
+        menu: MenuInput,
+        touch: &TouchControls,
+    ) -> bool {
+        if self.capturing {
+            // A controller or touch screen has no keys to bind, but can back out
+            if menu.back || touch.tapped_on(&touch::back_button()) {
+                self.capturing = false;
+                self.message = None;
+                return false;
+            }
+            let Some(key) = get_last_key_pressed() else {
+                return false;
+            };
+            self.capturing = false;
+            let action = Action::ALL[self.action];
+            if key == KeyCode::Escape {
+                self.message = None;
+            } else if !BINDABLE.contains(&key) {
+                self.message = Some(format!("{:?} can't be bound", key));
+            } else if let Some((player, other)) = bindings.conflict(self.player, action, Key(key)) {
+                self.message = Some(format!(
+                    "{:?} is already P{} {}",
+                    key,
+                    player + 1,
+                    other.label()
+                ));
+            } else {
+                bindings.players[self.player].insert(action, Key(key));
+                self.message = None;
+                if let Err(err) = bindings.save(storage) {
+                    error!("Could not save key bindings: {}", err);
+                }
+            }
+            return false;
+        }
+
+        let rows = Action::ALL.len();
+        if is_key_pressed(KeyCode::Up) || menu.up {
+            self.action = (self.action + rows - 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Down) || menu.down {
+            self.action = (self.action + 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Right) || menu.left || menu.right {
+            self.player = (self.player + 1) % MAX_PLAYERS;
+        }
+        if is_key_pressed(KeyCode::Enter) || menu.confirm {
+            self.capturing = true;
+            self.message = None;
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            *bindings = Bindings::default();
+            self.message = Some("Restored the default keys".to_string());
+            if let Err(err) = bindings.save(storage) {
+                error!("Could not save key bindings: {}", err);
+            }
+        }
+        is_key_pressed(KeyCode::Escape) || menu.back || touch.tapped_on(&touch::back_button())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        let bindings = Bindings::default();
+        for (player, keys) in bindings.players.iter().enumerate() {
+            for (action, key) in keys {
+                assert_eq!(bindings.conflict(player, *action, *key), None);
+            }
+        }
+    }
+
+    #[test]
+    fn same_key_on_two_actions_conflicts() {
+        let mut bindings = Bindings::default();
+        bindings.players[0].insert(Action::Bomb, Key(KeyCode::Space));
+        assert_eq!(
+            bindings.conflict(0, Action::Bomb, Key(KeyCode::Space)),
+            Some((0, Action::Fire))
+        );
+        assert_eq!(
+            bindings.conflict(0, Action::Fire, Key(KeyCode::Space)),
+            Some((0, Action::Bomb))
+        );
+    }
+
+    #[test]
+    fn same_key_across_players_conflicts() {
+        let bindings = Bindings::default();
+        assert_eq!(
+            bindings.conflict(1, Action::Fire, Key(KeyCode::Space)),
+            Some((0, Action::Fire))
+        );
+        assert_eq!(bindings.conflict(0, Action::MoveUp, Key(KeyCode::W)), Some((1, Action::MoveUp)));
+    }
+
+    #[test]
+    fn free_key_does_not_conflict() {
+        let bindings = Bindings::default();
+        assert_eq!(bindings.conflict(0, Action::Fire, Key(KeyCode::Z)), None);
+    }
+
+    #[test]
+    fn escape_cannot_be_bound() {
+        assert!(!BINDABLE.contains(&KeyCode::Escape));
+        let mut text = ron::to_string(&Bindings::default()).unwrap();
+        text = text.replace("\"P\"", "\"Escape\"");
+        assert!(Bindings::parse(&text).is_err());
+    }
+
+    #[test]
+    fn round_trips_and_fills_missing_players() {
+        let storage = MemoryStorage::default();
+        let mut bindings = Bindings::default();
+        bindings.players[0].insert(Action::Fire, Key(KeyCode::Z));
+        bindings.players.truncate(1);
+        bindings.save(&storage).unwrap();
+
+        let loaded = Bindings::load(&storage);
+        assert_eq!(loaded.key(0, Action::Fire), Some(Key(KeyCode::Z)));
+        assert_eq!(loaded.players.len(), MAX_PLAYERS);
+        assert_eq!(loaded.players[1], Bindings::default().players[1]);
+    }
+
+    #[test]
+    fn unreadable_file_is_backed_up() {
+        let storage = MemoryStorage::default();
+        storage.write(BINDINGS_FILE, b"(version: 99, players: [])").unwrap();
+        let loaded = Bindings::load(&storage);
+        assert_eq!(loaded.players, Bindings::default().players);
+        assert!(storage.read(&format!("{}.bak", BINDINGS_FILE)).is_ok());
+    }
+}
@@ -0,0 +1,32 @@
+#version 100
+
+// Lets bright things glow onto their surroundings, in a single cheap pass
+
+precision mediump float;
+
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 iResolution;
+
+// Only what is brighter than this glows
+#define THRESHOLD .6
+#define STRENGTH 1.5
+// Canvas pixels between samples
+#define SPACING 2.
+
+void main() {
+    vec2 texel = SPACING / iResolution;
+    vec3 col = texture2D(Texture, uv).rgb;
+
+    vec3 glow = vec3(0.);
+    for (int x = -3; x <= 3; x++) {
+        for (int y = -3; y <= 3; y++) {
+            vec3 nearby = texture2D(Texture, uv + vec2(float(x), float(y)) * texel).rgb;
+            glow += max(nearby - THRESHOLD, 0.);
+        }
+    }
+    col += glow / 49. * STRENGTH;
+
+    gl_FragColor = vec4(col, 1.);
+}
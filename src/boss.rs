@@ -0,0 +1,559 @@
+use crate::data::GameData;
+use crate::enemy::HIT_FLASH_TIME;
+use crate::pattern::PatternRunner;
+use crate::projectile::Projectile;
+use crate::shape::Shape;
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+pub const BOSSES_FILE: &str = "bosses.ron";
+
+// Pause between a phase change and the new phase's first attack
+const PHASE_CHANGE_REST: f32 = 1.0;
+// Seconds between explosions while a defeated boss breaks apart
+const OUTRO_EXPLOSION_INTERVAL: f32 = 0.2;
+
+// A boss as described in assets/bosses.ron
+#[derive(Clone, Debug, Deserialize)]
+pub struct BossDef {
+    pub name: String,
+    pub score: u32,
+    // Where the boss settles, as fractions of the screen size
+    pub home: (f32, f32),
+    pub intro_time: f32,
+    pub outro_time: f32,
+    pub parts: Vec<PartDef>,
+    pub phases: Vec<PhaseDef>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PartDef {
+    pub name: String,
+    pub texture: String,
+    pub frame_size: (u32, u32),
+    pub frames: u32,
+    pub fps: u32,
+    #[serde(default = "default_tint")]
+    pub tint: u32,
+    // Position relative to the boss' center
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+    pub health: u32,
+    // Score for shooting the part off
+    #[serde(default)]
+    pub score: u32,
+    // Destroying the core defeats the boss
+    #[serde(default)]
+    pub core: bool,
+}
+
+fn default_tint() -> u32 {
+    0xffffff
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PhaseDef {
+    // Health fraction at which the phase takes over
+    pub below: f32,
+    pub movement: Movement,
+    // Top speed while following the movement
+    pub speed: f32,
+    // Played in order, looping
+    pub attacks: Vec<AttackDef>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Movement {
+    Hold,
+    // Side to side around the home position
+    Sway { amplitude: f32, frequency: f32 },
+    // A figure of eight around the home position
+    Figure8 { amplitude: (f32, f32), frequency: f32 },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttackDef {
+    // Pattern name from assets/patterns
+    pub pattern: String,
+    // Part the pattern is fired from; skipped once the part is destroyed
+    pub from: String,
+    // Seconds to wait after the pattern finishes
+    #[serde(default)]
+    pub rest: f32,
+}
+
+impl BossDef {
+    pub fn parse_list(text: &str) -> Result<Vec<BossDef>, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn part_index(&self, name: &str) -> Option<usize> {
+        self.parts.iter().position(|part| part.name == name)
+    }
+
+    // Catch what would break the fight at load time: a boss needs parts,
+    // exactly one core to defeat it by, and attacks fired from its own parts
+    // with patterns that exist
+    pub fn validate(&self, has_pattern: impl Fn(&str) -> bool) -> Result<(), String> {
+        if self.parts.is_empty() {
+            return Err(format!("Boss {} has no parts", self.name));
+        }
+        let cores = self.parts.iter().filter(|part| part.core).count();
+        if cores != 1 {
+            return Err(format!("Boss {} has {} core parts, it needs exactly one", self.name, cores));
+        }
+        for attack in self.phases.iter().flat_map(|phase| &phase.attacks) {
+            if !has_pattern(&attack.pattern) {
+                return Err(format!("Boss {} uses unknown pattern {}", self.name, attack.pattern));
+            }
+            if self.part_index(&attack.from).is_none() {
+                return Err(format!("Boss {} fires from unknown part {}", self.name, attack.from));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BossState {
+    // Flying in, invulnerable
+    Intro,
+    Fighting,
+    // Breaking apart after the core was destroyed
+    Outro,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BossEvent {
+    Shot,
+    PhaseChanged(usize),
+    PartDestroyed { x: f32, y: f32, size: f32, score: u32 },
+    Defeated { score: u32 },
+    // One of the blasts while the boss breaks apart
+    Explosion { x: f32, y: f32, size: f32 },
+    Gone,
+}
+
+pub struct BossPart {
+    pub health: u32,
+    pub flash: f32,
+}
+
+impl BossPart {
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+}
+
+pub struct Boss {
+    // Index into the world's boss definitions
+    pub kind: usize,
+    // Position of the boss' center, used for interpolation
+    pub shape: Shape,
+    pub parts: Vec<BossPart>,
+    pub phase: usize,
+    pub state: BossState,
+    // Seconds spent in the current state
+    pub state_time: f32,
+    phase_time: f32,
+    // Height the intro starts from, above the screen
+    entry_y: f32,
+    attack: usize,
+    runner: Option<PatternRunner>,
+    rest: f32,
+    next_explosion: f32,
+}
+
+impl Boss {
+    pub fn new(kind: usize, data: &GameData, width: f32) -> Self {
+        let def = &data.bosses[kind];
+        let x = def.home.0 * width;
+        let y = -def
+            .parts
+            .iter()
+            .map(|part| part.offset.1.abs() + part.size.1)
+            .fold(0.0, f32::max);
+        Boss {
+            kind,
+            shape: Shape {
+                size: 0.0,
+                speed: 0.0,
+                x,
+                y,
+                prev_x: x,
+                prev_y: y,
+                color: WHITE,
+                collided: false,
+            },
+            parts: def
+                .parts
+                .iter()
+                .map(|part| BossPart {
+                    health: part.health,
+                    flash: 0.0,
+                })
+                .collect(),
+            phase: 0,
+            state: BossState::Intro,
+            state_time: 0.0,
+            phase_time: 0.0,
+            entry_y: y,
+            attack: 0,
+            runner: None,
+            rest: PHASE_CHANGE_REST,
+            next_explosion: 0.0,
+        }
+    }
+
+    pub fn def<'a>(&self, data: &'a GameData) -> &'a BossDef {
+        &data.bosses[self.kind]
+    }
+
+    pub fn health_fraction(&self, data: &GameData) -> f32 {
+        let def = self.def(data);
+        let total: u32 = def.parts.iter().map(|part| part.health).sum();
+        let left: u32 = self.parts.iter().map(|part| part.health).sum();
+        left as f32 / total.max(1) as f32
+    }
+
+    pub fn part_center(&self, data: &GameData, index: usize) -> Vec2 {
+        let offset = self.def(data).parts[index].offset;
+        vec2(self.shape.x + offset.0, self.shape.y + offset.1)
+    }
+
+    pub fn part_rect(&self, data: &GameData, index: usize) -> Rect {
+        let size = self.def(data).parts[index].size;
+        let center = self.part_center(data, index);
+        Rect::new(center.x - size.0 / 2.0, center.y - size.1 / 2.0, size.0, size.1)
+    }
+
+    // The first living part overlapping `rect`, if any
+    pub fn part_at(&self, data: &GameData, rect: &Rect) -> Option<usize> {
+        (0..self.parts.len())
+            .find(|&index| self.parts[index].is_alive() && self.part_rect(data, index).overlaps(rect))
+    }
+
+    pub fn is_vulnerable(&self) -> bool {
+        self.state == BossState::Fighting
+    }
+
+    pub fn damage(&mut self, data: &GameData, index: usize, damage: u32) -> Vec<BossEvent> {
+        let mut events = vec![];
+        if !self.is_vulnerable() || !self.parts[index].is_alive() {
+            return events;
+        }
+
+        let part = &mut self.parts[index];
+        part.health = part.health.saturating_sub(damage);
+        part.flash = HIT_FLASH_TIME;
+        if part.is_alive() {
+            return events;
+        }
+
+        let def = self.def(data);
+        let center = self.part_center(data, index);
+        let part_def = &def.parts[index];
+        events.push(BossEvent::PartDestroyed {
+            x: center.x,
+            y: center.y,
+            size: part_def.size.0.max(part_def.size.1),
+            score: part_def.score,
+        });
+        if part_def.core {
+            // Take everything else down with the core
+            for part in &mut self.parts {
+                part.health = 0;
+            }
+            self.state = BossState::Outro;
+            self.state_time = 0.0;
+            self.runner = None;
+            events.push(BossEvent::Defeated { score: def.score });
+        }
+        events
+    }
+
+    pub fn update(
+        &mut self,
+        dt: f32,
+        data: &GameData,
+        width: f32,
+        height: f32,
+        target: Vec2,
+        bullets: &mut Vec<Projectile>,
+    ) -> Vec<BossEvent> {
+        let mut events = vec![];
+        let def = &data.bosses[self.kind];
+        self.state_time += dt;
+        for part in &mut self.parts {
+            part.flash = (part.flash - dt).max(0.0);
+        }
+        let home = vec2(def.home.0 * width, def.home.1 * height);
+
+        match self.state {
+            BossState::Intro => {
+                // Ease down from above the screen to the home position
+                let t = (self.state_time / def.intro_time.max(f32::EPSILON)).min(1.0);
+                let eased = 1.0 - (1.0 - t) * (1.0 - t);
+                self.shape.y = self.entry_y + (home.y - self.entry_y) * eased;
+                if t >= 1.0 {
+                    self.shape.y = home.y;
+                    self.state = BossState::Fighting;
+                    self.state_time = 0.0;
+                    events.push(BossEvent::PhaseChanged(0));
+                }
+            }
+            BossState::Fighting => {
+                // Switch phase once health drops below the next threshold
+                let health = self.health_fraction(data);
+                let phase = def
+                    .phases
+                    .iter()
+                    .rposition(|phase| health <= phase.below)
+                    .unwrap_or(0);
+                if phase != self.phase {
+                    self.phase = phase;
+                    self.phase_time = 0.0;
+                    self.attack = 0;
+                    self.runner = None;
+                    self.rest = PHASE_CHANGE_REST;
+                    events.push(BossEvent::PhaseChanged(phase));
+                }
+                self.phase_time += dt;
+
+                if let Some(phase) = def.phases.get(self.phase) {
+                    self.follow(phase, home, dt);
+                    if self.attack(data, phase, dt, target, bullets) {
+                        events.push(BossEvent::Shot);
+                    }
+                }
+            }
+            BossState::Outro => {
+                // Shake and blow up bit by bit, then vanish
+                self.shape.x += (self.state_time * 60.0).sin() * 40.0 * dt;
+                self.next_explosion -= dt;
+                if self.next_explosion <= 0.0 {
+                    self.next_explosion = OUTRO_EXPLOSION_INTERVAL;
+                    let index = (self.state_time / OUTRO_EXPLOSION_INTERVAL) as usize % def.parts.len();
+                    let center = self.part_center(data, index);
+                    let size = def.parts[index].size;
+                    events.push(BossEvent::Explosion {
+                        x: center.x,
+                        y: center.y,
+                        size: size.0.max(size.1),
+                    });
+                }
+                if self.state_time >= def.outro_time {
+                    events.push(BossEvent::Gone);
+                }
+            }
+        }
+        events
+    }
+
+    // Head for where the phase's movement wants the boss, at a limited speed
+    fn follow(&mut self, phase: &PhaseDef, home: Vec2, dt: f32) {
+        let t = self.phase_time;
+        let goal = match phase.movement {
+            Movement::Hold => home,
+            Movement::Sway {
+                amplitude,
+                frequency,
+            } => home + vec2(amplitude * (t * frequency * std::f32::consts::TAU).sin(), 0.0),
+            Movement::Figure8 {
+                amplitude,
+                frequency,
+            } => {
+                let angle = t * frequency * std::f32::consts::TAU;
+                home + vec2(amplitude.0 * angle.sin(), amplitude.1 * (2.0 * angle).sin())
+            }
+        };
+        let position = vec2(self.shape.x, self.shape.y);
+        let step = (goal - position).clamp_length_max(phase.speed * dt);
+        self.shape.x += step.x;
+        self.shape.y += step.y;
+    }
+
+    // Run the phase's attacks in turn, returning true if anything was fired
+    fn attack(
+        &mut self,
+        data: &GameData,
+        phase: &PhaseDef,
+        dt: f32,
+        target: Vec2,
+        bullets: &mut Vec<Projectile>,
+    ) -> bool {
+        if phase.attacks.is_empty() {
+            return false;
+        }
+        let def = self.def(data);
+
+        if self.runner.is_none() {
+            self.rest -= dt;
+            if self.rest > 0.0 {
+                return false;
+            }
+            // Skip attacks from parts that have been shot off
+            for _ in 0..phase.attacks.len() {
+                let attack = &phase.attacks[self.attack % phase.attacks.len()];
+                let alive = def
+                    .part_index(&attack.from)
+                    .is_some_and(|index| self.parts[index].is_alive());
+                if alive {
+                    if let Some(pattern) = data.patterns.get(&attack.pattern) {
+                        self.runner = Some(PatternRunner::new(pattern.clone()));
+                    }
+                    break;
+                }
+                self.attack += 1;
+            }
+            if self.runner.is_none() {
+                return false;
+            }
+        }
+
+        let attack = &phase.attacks[self.attack % phase.attacks.len()];
+        let Some(index) = def.part_index(&attack.from) else {
+            return false;
+        };
+        let origin = self.part_center(data, index);
+        let Some(runner) = &mut self.runner else {
+            return false;
+        };
+        let fired = runner.update(dt, origin, target, bullets) > 0;
+        if runner.is_finished() || !self.parts[index].is_alive() {
+            self.runner = None;
+            self.rest = attack.rest;
+            self.attack += 1;
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    // Step `seconds`, collecting the events
+    fn run(boss: &mut Boss, data: &GameData, seconds: f32, bullets: &mut Vec<Projectile>) -> Vec<BossEvent> {
+        let mut events = vec![];
+        for _ in 0..(seconds / DT).round() as usize {
+            events.extend(boss.update(DT, data, 800.0, 600.0, vec2(400.0, 500.0), bullets));
+        }
+        events
+    }
+
+    // The bundled boss, done with its intro
+    fn fighting(data: &GameData) -> Boss {
+        let mut boss = Boss::new(0, data, 800.0);
+        let events = run(&mut boss, data, data.bosses[0].intro_time + DT, &mut vec![]);
+        assert_eq!(events, vec![BossEvent::PhaseChanged(0)]);
+        assert!(boss.is_vulnerable());
+        boss
+    }
+
+    fn part(boss: &Boss, data: &GameData, name: &str) -> usize {
+        boss.def(data).part_index(name).unwrap()
+    }
+
+    #[test]
+    fn intro_cannot_be_hurt() {
+        let data = data::bundled();
+        let mut boss = Boss::new(0, &data, 800.0);
+        let core = part(&boss, &data, "core");
+        assert!(boss.damage(&data, core, 1000).is_empty());
+        assert_eq!(boss.health_fraction(&data), 1.0);
+    }
+
+    #[test]
+    fn phase_changes_below_its_threshold() {
+        let data = data::bundled();
+        let mut boss = fighting(&data);
+        let (left, core) = (part(&boss, &data, "left_cannon"), part(&boss, &data, "core"));
+
+        let events = boss.damage(&data, left, 1000);
+        assert!(matches!(events[..], [BossEvent::PartDestroyed { score: 500, .. }]));
+        assert!(boss.health_fraction(&data) > data.bosses[0].phases[1].below);
+        assert!(!run(&mut boss, &data, DT, &mut vec![]).contains(&BossEvent::PhaseChanged(1)));
+
+        boss.damage(&data, core, 30);
+        assert!(boss.health_fraction(&data) <= data.bosses[0].phases[1].below);
+        assert!(run(&mut boss, &data, DT, &mut vec![]).contains(&BossEvent::PhaseChanged(1)));
+        assert_eq!(boss.phase, 1);
+    }
+
+    #[test]
+    fn destroying_the_core_defeats_the_boss() {
+        let data = data::bundled();
+        let mut boss = fighting(&data);
+        let core = part(&boss, &data, "core");
+
+        let events = boss.damage(&data, core, 1000);
+        assert!(matches!(events[0], BossEvent::PartDestroyed { .. }));
+        assert_eq!(events[1], BossEvent::Defeated { score: data.bosses[0].score });
+        assert_eq!(boss.state, BossState::Outro);
+        assert!(boss.parts.iter().all(|part| !part.is_alive()));
+        assert!(!boss.is_vulnerable());
+
+        let mut bullets = vec![];
+        let events = run(&mut boss, &data, data.bosses[0].outro_time + DT, &mut bullets);
+        assert!(events.iter().any(|event| matches!(event, BossEvent::Explosion { .. })));
+        assert_eq!(events.last(), Some(&BossEvent::Gone));
+        assert!(bullets.is_empty());
+    }
+
+    #[test]
+    fn attacks_from_destroyed_parts_are_skipped() {
+        let data = data::bundled();
+        let mut boss = fighting(&data);
+        for name in ["left_cannon", "right_cannon"] {
+            let index = part(&boss, &data, name);
+            boss.damage(&data, index, 1000);
+        }
+        // Still the first phase, which also fires from the cannons
+        assert!(boss.health_fraction(&data) > data.bosses[0].phases[1].below);
+
+        let attacks = &data.bosses[0].phases[0].attacks;
+        let mut bullets = vec![];
+        let mut shots = 0;
+        for _ in 0..(10.0 / DT) as usize {
+            let events = boss.update(DT, &data, 800.0, 600.0, vec2(400.0, 500.0), &mut bullets);
+            shots += events.iter().filter(|event| **event == BossEvent::Shot).count();
+            if boss.runner.is_some() {
+                assert_eq!(attacks[boss.attack % attacks.len()].from, "core");
+            }
+        }
+        assert_eq!(boss.phase, 0);
+        assert!(shots > 0);
+    }
+
+    #[test]
+    fn validate_rejects_broken_bosses() {
+        let data = data::bundled();
+        let has_pattern = |name: &str| data.patterns.contains_key(name);
+        let boss = &data.bosses[0];
+        assert!(boss.validate(has_pattern).is_ok());
+
+        let mut broken = boss.clone();
+        broken.parts.clear();
+        assert!(broken.validate(has_pattern).is_err());
+
+        let mut broken = boss.clone();
+        broken.parts.iter_mut().for_each(|part| part.core = false);
+        assert!(broken.validate(has_pattern).is_err());
+
+        let mut broken = boss.clone();
+        broken.parts.iter_mut().for_each(|part| part.core = true);
+        assert!(broken.validate(has_pattern).is_err());
+
+        let mut broken = boss.clone();
+        broken.phases[0].attacks[0].pattern = "missing".to_string();
+        assert!(broken.validate(has_pattern).is_err());
+
+        let mut broken = boss.clone();
+        broken.phases[0].attacks[0].from = "missing".to_string();
+        assert!(broken.validate(has_pattern).is_err());
+    }
+}
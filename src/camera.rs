@@ -0,0 +1,102 @@
+use crate::canvas;
+use macroquad::prelude::*;
+
+// Trauma lost per second; shake grows with its square so small knocks stay
+// subtle
+const TRAUMA_DECAY: f32 = 1.2;
+const MAX_SHAKE_OFFSET: f32 = 14.0;
+// Degrees
+const MAX_SHAKE_ANGLE: f32 = 2.5;
+// How fast the shake wobbles
+const SHAKE_FREQUENCY: f32 = 25.0;
+// Zoom while a boss flies in, and how quickly the camera gets there
+const BOSS_ZOOM: f32 = 1.25;
+const ZOOM_RATE: f32 = 3.0;
+
+// Shakes, freezes and zooms the view of the playfield. The simulation never
+// sees any of it, so replays are unaffected.
+pub struct GameCamera {
+    trauma: f32,
+    // Seconds left of the current freeze
+    hit_stop: f32,
+    zoom: f32,
+    // Where the camera zooms in on
+    focus: Vec2,
+    time: f32,
+}
+
+impl Default for GameCamera {
+    fn default() -> Self {
+        GameCamera {
+            trauma: 0.0,
+            hit_stop: 0.0,
+            zoom: 1.0,
+            focus: vec2(canvas::WIDTH, canvas::HEIGHT) / 2.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl GameCamera {
+    // Start a run with the view at rest
+    pub fn reset(&mut self) {
+        *self = GameCamera::default();
+    }
+
+    // Shake harder, from 0 for nothing to 1 for everything at once
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    // Freeze the action for a moment to let a hit land
+    pub fn hit_stop(&mut self, seconds: f32) {
+        self.hit_stop = self.hit_stop.max(seconds);
+    }
+
+    // True while the simulation should hold still
+    pub fn frozen(&self) -> bool {
+        self.hit_stop > 0.0
+    }
+
+    // Ease towards `focus`, zoomed in, or back out to the whole playfield
+    pub fn update(&mut self, dt: f32, focus: Option<Vec2>) {
+        self.time += dt;
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.hit_stop = (self.hit_stop - dt).max(0.0);
+        let target = if focus.is_some() { BOSS_ZOOM } else { 1.0 };
+        let blend = 1.0 - (-ZOOM_RATE * dt).exp();
+        self.zoom += (target - self.zoom) * blend;
+        if let Some(focus) = focus {
+            self.focus = focus;
+        }
+    }
+
+    // The canvas' camera, shaken and zoomed
+    pub fn view(&self, base: &Camera2D) -> Camera2D {
+        let center = vec2(canvas::WIDTH, canvas::HEIGHT) / 2.0;
+        // Keep the zoomed view inside the playfield
+        let half = center / self.zoom;
+        let zoomed = (BOSS_ZOOM - 1.0).max(f32::EPSILON);
+        let target = center.lerp(self.focus, ((self.zoom - 1.0) / zoomed).clamp(0.0, 1.0))
+            .clamp(half, vec2(canvas::WIDTH, canvas::HEIGHT) - half);
+
+        let shake = self.trauma * self.trauma;
+        let t = self.time * SHAKE_FREQUENCY;
+        // Out-of-step waves stand in for noise without touching the game's RNG
+        let offset = vec2(
+            t.sin() + (t * 2.3).sin() * 0.5,
+            (t * 1.3).cos() + (t * 2.9).sin() * 0.5,
+        ) / 1.5
+            * MAX_SHAKE_OFFSET
+            * shake;
+
+        Camera2D {
+            target: target + offset,
+            zoom: base.zoom * self.zoom,
+            rotation: (t * 0.7).sin() * MAX_SHAKE_ANGLE * shake,
+            offset: base.offset,
+            render_target: base.render_target.clone(),
+            viewport: base.viewport,
+        }
+    }
+}
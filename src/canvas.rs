@@ -0,0 +1,101 @@
+use macroquad::prelude::*;
+
+// Size of the playfield, which everything is simulated and drawn at whatever
+// the size of the window
+pub const WIDTH: f32 = 800.0;
+pub const HEIGHT: f32 = 600.0;
+
+// Off-screen canvas at the logical resolution, scaled to fit the window with
+// black bars around it
+pub struct Canvas {
+    target: RenderTarget,
+    camera: Camera2D,
+    // Only scale by whole multiples, for sharp pixels
+    integer_scaling: bool,
+}
+
+impl Canvas {
+    pub fn new(integer_scaling: bool) -> Self {
+        let target = render_target(WIDTH as u32, HEIGHT as u32);
+        let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, WIDTH, HEIGHT));
+        camera.render_target = Some(target.clone());
+        Canvas {
+            target,
+            camera,
+            integer_scaling,
+        }
+    }
+
+    pub fn integer_scaling(&self) -> bool {
+        self.integer_scaling
+    }
+
+    pub fn set_integer_scaling(&mut self, integer_scaling: bool) {
+        self.integer_scaling = integer_scaling;
+    }
+
+    // Looks at the whole canvas, unscaled
+    pub fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    // Everything drawn until `present` lands on the canvas
+    pub fn begin(&self) {
+        set_camera(&self.camera);
+    }
+
+    // What has been drawn so far
+    pub fn texture(&self) -> &Texture2D {
+        &self.target.texture
+    }
+
+    // Scale `frame`, the canvas or a post-processed copy of it, onto the window
+    pub fn present(&self, frame: &Texture2D) {
+        set_default_camera();
+        clear_background(BLACK);
+        frame.set_filter(if self.integer_scaling {
+            FilterMode::Nearest
+        } else {
+            FilterMode::Linear
+        });
+        let viewport = self.viewport();
+        draw_texture_ex(
+            frame,
+            viewport.x,
+            viewport.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(viewport.size()),
+                // Render targets come out upside down
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    // Where the canvas sits in the window
+    pub fn viewport(&self) -> Rect {
+        let mut scale = (screen_width() / WIDTH).min(screen_height() / HEIGHT);
+        // Windows smaller than the canvas still get all of it
+        if self.integer_scaling && scale >= 1.0 {
+            scale = scale.floor();
+        }
+        let (width, height) = (WIDTH * scale, HEIGHT * scale);
+        Rect::new(
+            ((screen_width() - width) / 2.0).round(),
+            ((screen_height() - height) / 2.0).round(),
+            width,
+            height,
+        )
+    }
+
+    // A point in the window, like the mouse cursor or a touch, on the canvas
+    pub fn to_logical(&self, position: Vec2) -> Vec2 {
+        let viewport = self.viewport();
+        // A minimized window has no canvas to map onto
+        if viewport.w <= 0.0 {
+            return position;
+        }
+        (position - viewport.point()) * WIDTH / viewport.w
+    }
+}
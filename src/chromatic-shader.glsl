@@ -0,0 +1,22 @@
+#version 100
+
+// Splits red and blue apart towards the edges, like a cheap lens
+
+precision mediump float;
+
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 iResolution;
+
+// Canvas pixels the colors drift apart at the edges
+#define SPREAD 3.
+
+void main() {
+    vec2 direction = (uv - .5) * 2. * SPREAD / iResolution;
+    float r = texture2D(Texture, uv + direction).r;
+    float g = texture2D(Texture, uv).g;
+    float b = texture2D(Texture, uv - direction).b;
+
+    gl_FragColor = vec4(r, g, b, 1.);
+}
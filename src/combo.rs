@@ -0,0 +1,63 @@
+// Seconds after a kill or graze in which the next one keeps the chain going
+pub const CHAIN_WINDOW: f32 = 2.0;
+// Kills needed for each step of the chain multiplier
+const KILLS_PER_STEP: u32 = 5;
+const MAX_CHAIN_MULTIPLIER: u32 = 8;
+// How much one grazed shot fills the meter, which tops out at 1
+const GRAZE_FILL: f32 = 0.08;
+// Full graze meters each add one to the multiplier, up to this many
+const MAX_GRAZE_LEVEL: u32 = 3;
+
+// Kill chains and the graze meter, which together multiply the score
+#[derive(Default)]
+pub struct Combo {
+    pub chain: u32,
+    // Seconds left before the chain breaks
+    pub timer: f32,
+    pub graze: f32,
+    pub graze_level: u32,
+    pub best_chain: u32,
+}
+
+impl Combo {
+    pub fn update(&mut self, dt: f32) {
+        if self.chain == 0 {
+            return;
+        }
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            self.chain = 0;
+            self.graze_level = 0;
+        }
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.chain / KILLS_PER_STEP).min(MAX_CHAIN_MULTIPLIER) + self.graze_level
+    }
+
+    pub fn kill(&mut self) {
+        self.chain += 1;
+        self.best_chain = self.best_chain.max(self.chain);
+        self.timer = CHAIN_WINDOW;
+    }
+
+    // A shot passed close to the ship without hitting it
+    pub fn graze(&mut self) {
+        if self.chain > 0 {
+            self.timer = CHAIN_WINDOW;
+        }
+        self.graze += GRAZE_FILL;
+        if self.graze >= 1.0 {
+            self.graze = 0.0;
+            self.graze_level = (self.graze_level + 1).min(MAX_GRAZE_LEVEL);
+        }
+    }
+
+    // Losing a ship drops the chain, but the best one is kept for the run
+    pub fn reset(&mut self) {
+        *self = Combo {
+            best_chain: self.best_chain,
+            ..Default::default()
+        };
+    }
+}
@@ -0,0 +1,31 @@
+#version 100
+
+// Bulges the picture like an old tube screen and darkens its corners
+
+precision mediump float;
+
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 iResolution;
+
+// Lower bulges more
+#define CURVATURE 5.
+
+void main() {
+    vec2 centered = uv * 2. - 1.;
+    vec2 offset = centered.yx / CURVATURE;
+    centered += centered * offset * offset;
+    vec2 curved = centered * .5 + .5;
+
+    if (curved.x < 0. || curved.x > 1. || curved.y < 0. || curved.y > 1.) {
+        gl_FragColor = vec4(0., 0., 0., 1.);
+        return;
+    }
+
+    vec3 col = texture2D(Texture, curved).rgb;
+    float vignette = curved.x * curved.y * (1. - curved.x) * (1. - curved.y);
+    col *= clamp(pow(16. * vignette, .25), 0., 1.);
+
+    gl_FragColor = vec4(col, 1.);
+}
@@ -0,0 +1,64 @@
+use crate::boss::BossDef;
+use crate::difficulty::Difficulty;
+use crate::enemy::EnemyArchetype;
+use crate::level::Level;
+use crate::pattern::Pattern;
+use crate::weapon::WeaponDef;
+use std::{collections::HashMap, rc::Rc};
+
+// Gameplay content loaded from the assets folder, shared by the simulation
+// and the renderer
+pub struct GameData {
+    pub enemies: Vec<EnemyArchetype>,
+    // Bullet patterns keyed by name
+    pub patterns: HashMap<String, Rc<Pattern>>,
+    // Stages in the order they are played
+    pub levels: Vec<Level>,
+    pub bosses: Vec<BossDef>,
+    pub weapon: WeaponDef,
+    pub difficulties: Vec<Difficulty>,
+}
+
+impl GameData {
+    pub fn archetype_index(&self, name: &str) -> Option<usize> {
+        self.enemies.iter().position(|archetype| archetype.name == name)
+    }
+
+    pub fn difficulty_index(&self, name: &str) -> Option<usize> {
+        self.difficulties.iter().position(|difficulty| difficulty.name == name)
+    }
+
+    pub fn boss_index(&self, name: &str) -> Option<usize> {
+        self.bosses.iter().position(|boss| boss.name == name)
+    }
+}
+
+// The bundled assets, parsed without a window for headless tests
+#[cfg(test)]
+pub fn bundled() -> Rc<GameData> {
+    use crate::{boss, difficulty, enemy, level, pattern, weapon};
+
+    let patterns = [
+        include_str!("../assets/patterns/spiral.ron"),
+        include_str!("../assets/patterns/aimed_fan.ron"),
+        include_str!("../assets/patterns/ring_burst.ron"),
+        include_str!("../assets/patterns/flower.ron"),
+    ]
+    .into_iter()
+    .map(|text| {
+        let pattern = pattern::Pattern::parse(text).unwrap();
+        (pattern.name.clone(), Rc::new(pattern))
+    })
+    .collect();
+    Rc::new(GameData {
+        enemies: enemy::parse_archetypes(include_str!("../assets/enemies.ron")).unwrap(),
+        patterns,
+        levels: vec![
+            level::Level::parse(include_str!("../assets/levels/stage1.ron")).unwrap(),
+            level::Level::parse(include_str!("../assets/levels/stage2.ron")).unwrap(),
+        ],
+        bosses: boss::BossDef::parse_list(include_str!("../assets/bosses.ron")).unwrap(),
+        weapon: weapon::WeaponDef::parse(include_str!("../assets/weapon.ron")).unwrap(),
+        difficulties: difficulty::parse_difficulties(include_str!("../assets/difficulties.ron")).unwrap(),
+    })
+}
@@ -0,0 +1,65 @@
+use serde::Deserialize;
+
+pub const DIFFICULTIES_FILE: &str = "difficulties.ron";
+pub const DEFAULT_DIFFICULTY: &str = "Normal";
+
+// A difficulty preset as described in assets/difficulties.ron
+#[derive(Clone, Debug, Deserialize)]
+pub struct Difficulty {
+    pub name: String,
+    // Ships per credit
+    pub lives: u32,
+    // Extra credits after the last ship is lost
+    pub continues: u32,
+    // Seconds without a shield before one comes up on its own, None if
+    // shields only come from power-ups
+    #[serde(default)]
+    pub shield_regen: Option<f32>,
+    // Seconds the ship blinks and can't be hurt after respawning
+    pub invulnerability: f32,
+    // Multipliers for how quickly enemies come in and how fast they fly
+    pub spawn_rate: f32,
+    pub speed: f32,
+    // Seconds of play, and points scored, that each add one to the intensity
+    pub ramp_time: f32,
+    pub ramp_score: u32,
+    pub max_intensity: f32,
+}
+
+pub fn parse_difficulties(text: &str) -> Result<Vec<Difficulty>, ron::error::SpannedError> {
+    ron::from_str(text)
+}
+
+// Speed gained per point of intensity above 1
+const SPEED_RAMP: f32 = 0.15;
+
+// Turns up the pressure as a run goes on, scaled by the preset
+#[derive(Clone, Copy, Debug)]
+pub struct Director {
+    // 1 at the start of a run, growing with time and score
+    pub intensity: f32,
+    // Multiplier for how quickly waves and endless enemies come in
+    pub spawn_rate: f32,
+    // Multiplier for enemy speeds
+    pub speed: f32,
+}
+
+impl Director {
+    pub fn new(preset: &Difficulty) -> Self {
+        let mut director = Director {
+            intensity: 1.0,
+            spawn_rate: 1.0,
+            speed: 1.0,
+        };
+        director.update(preset, 0.0, 0);
+        director
+    }
+
+    pub fn update(&mut self, preset: &Difficulty, time: f64, score: u32) {
+        let from_time = time as f32 / preset.ramp_time.max(1.0);
+        let from_score = score as f32 / preset.ramp_score.max(1) as f32;
+        self.intensity = (1.0 + from_time + from_score).min(preset.max_intensity);
+        self.spawn_rate = preset.spawn_rate * self.intensity;
+        self.speed = preset.speed * (1.0 + SPEED_RAMP * (self.intensity - 1.0));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::level::Path;
+use crate::pattern::PatternRunner;
+use crate::projectile::ProjectileKind;
+use crate::rng::Rng;
+use crate::shape::Shape;
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+pub const ENEMIES_FILE: &str = "enemies.ron";
+
+// An enemy type as described in assets/enemies.ron
+#[derive(Clone, Debug, Deserialize)]
+pub struct EnemyArchetype {
+    pub name: String,
+    pub texture: String,
+    pub frame_size: (u32, u32),
+    pub frames: u32,
+    pub fps: u32,
+    pub size: (f32, f32),
+    pub speed: (f32, f32),
+    pub score: u32,
+    #[serde(default = "default_hit_points")]
+    pub hit_points: u32,
+    pub hitbox: (f32, f32),
+    #[serde(default = "default_tint")]
+    pub tint: u32,
+    pub spawn_weight: f32,
+    // How much the spawn weight grows with each point of intensity
+    #[serde(default)]
+    pub ramp: f32,
+    // Chance of leaving a power-up behind when destroyed
+    #[serde(default = "default_drop_chance")]
+    pub drop_chance: f32,
+    #[serde(default)]
+    pub fire: Option<FireConfig>,
+}
+
+// How an archetype shoots at the player
+#[derive(Clone, Debug, Deserialize)]
+pub struct FireConfig {
+    pub pattern: FirePattern,
+    // Seconds between volleys, rolled after every volley
+    pub cooldown: (f32, f32),
+    pub speed: f32,
+    pub projectile: ProjectileKind,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum FirePattern {
+    // Straight down
+    Straight,
+    // At the player's position when fired
+    Aimed,
+    // `count` shots fanned over `angle` degrees, centered on the player
+    Spread { count: u32, angle: f32 },
+    // A pattern from assets/patterns, by name
+    Script(String),
+}
+
+impl FirePattern {
+    // Velocities of the projectiles in one volley
+    pub fn velocities(&self, origin: Vec2, target: Vec2, speed: f32) -> Vec<Vec2> {
+        let aim = (target - origin).try_normalize().unwrap_or(vec2(0.0, 1.0));
+        match self {
+            FirePattern::Straight => vec![vec2(0.0, speed)],
+            FirePattern::Aimed => vec![aim * speed],
+            FirePattern::Script(_) => vec![],
+            FirePattern::Spread { count, angle } => {
+                let count = (*count).max(1);
+                let (start, step) = if count > 1 {
+                    let angle = angle.to_radians();
+                    (-angle / 2.0, angle / (count - 1) as f32)
+                } else {
+                    (0.0, 0.0)
+                };
+                (0..count)
+                    .map(|i| Vec2::from_angle(start + step * i as f32).rotate(aim) * speed)
+                    .collect()
+            }
+        }
+    }
+}
+
+fn default_tint() -> u32 {
+    0xffffff
+}
+
+fn default_hit_points() -> u32 {
+    1
+}
+
+fn default_drop_chance() -> f32 {
+    0.05
+}
+
+pub fn parse_archetypes(text: &str) -> Result<Vec<EnemyArchetype>, ron::error::SpannedError> {
+    ron::from_str(text)
+}
+
+impl EnemyArchetype {
+    // Spawn weight once the director has reached `intensity`
+    pub fn weight_at(&self, intensity: f32) -> f32 {
+        self.spawn_weight * (1.0 + self.ramp * (intensity - 1.0))
+    }
+}
+
+// Pick an archetype index at random, weighted by `spawn_weight` and `ramp`
+pub fn choose_archetype(archetypes: &[EnemyArchetype], intensity: f32, rng: &mut Rng) -> Option<usize> {
+    let total: f32 = archetypes.iter().map(|a| a.weight_at(intensity)).sum();
+    let mut roll = rng.gen_range(0.0, total);
+    for (index, archetype) in archetypes.iter().enumerate() {
+        let weight = archetype.weight_at(intensity);
+        if roll < weight {
+            return Some(index);
+        }
+        roll -= weight;
+    }
+    archetypes.len().checked_sub(1)
+}
+
+// How long an enemy flashes after taking a non-lethal hit
+pub const HIT_FLASH_TIME: f32 = 0.08;
+// Seconds a swooping enemy takes to glide across
+const SWOOP_TIME: f32 = 2.0;
+
+pub struct Enemy {
+    pub shape: Shape,
+    // Index into the world's archetype list
+    pub kind: usize,
+    pub health: u32,
+    // Remaining time of the hit flash
+    pub flash: f32,
+    // Time until the next volley, for archetypes that fire
+    pub fire_cooldown: f32,
+    // Scripted pattern currently being fired
+    pub pattern: Option<PatternRunner>,
+    pub path: Path,
+    // Where the enemy entered, for paths that move sideways
+    pub origin_x: f32,
+    // Seconds spent on the path so far
+    pub path_time: f32,
+    // Seconds spent hovering, for `Path::Hover`
+    pub hover_time: f32,
+}
+
+impl Enemy {
+    pub fn advance(&mut self, dt: f32, width: f32, height: f32) {
+        self.path_time += dt;
+        let shape = &mut self.shape;
+        match self.path {
+            Path::Straight => shape.y += shape.speed * dt,
+            Path::Sine {
+                amplitude,
+                frequency,
+            } => {
+                shape.y += shape.speed * dt;
+                shape.x = self.origin_x
+                    + amplitude * (self.path_time * frequency * std::f32::consts::TAU).sin();
+            }
+            Path::Swoop { to } => {
+                shape.y += shape.speed * dt;
+                let t = (self.path_time / SWOOP_TIME).min(1.0);
+                let eased = t * t * (3.0 - 2.0 * t);
+                shape.x = self.origin_x + (to * width - self.origin_x) * eased;
+            }
+            Path::Hover { at, time } => {
+                if shape.y < at * height || self.hover_time >= time {
+                    shape.y += shape.speed * dt;
+                } else {
+                    self.hover_time += dt;
+                }
+            }
+        }
+        self.flash = (self.flash - dt).max(0.0);
+    }
+
+    // Returns true if the damage destroyed the enemy
+    pub fn take_damage(&mut self, damage: u32) -> bool {
+        self.health = self.health.saturating_sub(damage);
+        if self.health == 0 {
+            self.shape.collided = true;
+        } else {
+            self.flash = HIT_FLASH_TIME;
+        }
+        self.shape.collided
+    }
+
+    // The part of the sprite that collides, scaled by the archetype's hitbox
+    pub fn hitbox(&self, archetype: &EnemyArchetype) -> Rect {
+        let w = self.shape.size * archetype.hitbox.0;
+        let h = self.shape.size * archetype.hitbox.1;
+        Rect::new(self.shape.x - w / 2.0, self.shape.y - h / 2.0, w, h)
+    }
+}
@@ -0,0 +1,162 @@
+use crate::input::Input;
+use crate::player::MAX_PLAYERS;
+#[cfg(not(target_arch = "wasm32"))]
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use macroquad::prelude::*;
+
+// How far the stick has to be pushed before the ship moves, as a fraction of
+// full tilt
+const DEADZONE: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadEvent {
+    // A controller was plugged in and now drives this player
+    Connected(usize),
+    // The controller driving this player went away
+    Disconnected(usize),
+}
+
+// Menu moves made on any controller during the current frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MenuInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    // South or Start
+    pub confirm: bool,
+    // East
+    pub back: bool,
+}
+
+// Controllers, each driving a player in the order they were plugged in. Web
+// builds have none and only see the keyboard.
+pub struct Gamepads {
+    #[cfg(not(target_arch = "wasm32"))]
+    gilrs: Option<Gilrs>,
+    #[cfg(not(target_arch = "wasm32"))]
+    slots: [Option<GamepadId>; MAX_PLAYERS],
+    // Start pressed during the current frame, by player
+    #[cfg(not(target_arch = "wasm32"))]
+    start: [bool; MAX_PLAYERS],
+    pub menu: MenuInput,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Gamepads {
+    pub fn open() -> Self {
+        let gilrs = Gilrs::new()
+            .map_err(|err| warn!("Controllers unavailable: {}", err))
+            .ok();
+        let mut slots = [None; MAX_PLAYERS];
+        if let Some(gilrs) = &gilrs {
+            for ((id, _), slot) in gilrs.gamepads().zip(&mut slots) {
+                *slot = Some(id);
+            }
+        }
+        Gamepads {
+            gilrs,
+            slots,
+            start: [false; MAX_PLAYERS],
+            menu: MenuInput::default(),
+        }
+    }
+
+    // Take in what happened since the last frame; call once per frame
+    pub fn update(&mut self) -> Vec<PadEvent> {
+        self.start = [false; MAX_PLAYERS];
+        self.menu = MenuInput::default();
+        let mut events = vec![];
+        let Some(gilrs) = &mut self.gilrs else {
+            return events;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            let slot = self.slots.iter().position(|slot| *slot == Some(event.id));
+            match event.event {
+                EventType::Connected => {
+                    // Controllers already there at start-up may report in again
+                    if slot.is_some() {
+                        continue;
+                    }
+                    if let Some(free) = self.slots.iter().position(Option::is_none) {
+                        self.slots[free] = Some(event.id);
+                        events.push(PadEvent::Connected(free));
+                    }
+                }
+                EventType::Disconnected => {
+                    if let Some(slot) = slot {
+                        self.slots[slot] = None;
+                        events.push(PadEvent::Disconnected(slot));
+                    }
+                }
+                EventType::ButtonPressed(button, _) => match button {
+                    Button::DPadUp => self.menu.up = true,
+                    Button::DPadDown => self.menu.down = true,
+                    Button::DPadLeft => self.menu.left = true,
+                    Button::DPadRight => self.menu.right = true,
+                    Button::South => self.menu.confirm = true,
+                    Button::East => self.menu.back = true,
+                    Button::Start => {
+                        self.menu.confirm = true;
+                        if let Some(slot) = slot {
+                            self.start[slot] = true;
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        events
+    }
+
+    // The controls of the controller driving `player`, idle without one
+    pub fn input(&self, player: usize) -> Input {
+        let (Some(gilrs), Some(Some(id))) = (&self.gilrs, self.slots.get(player)) else {
+            return Input::default();
+        };
+        let pad = gilrs.gamepad(*id);
+        // gilrs points the Y axis up
+        let stick = vec2(pad.value(Axis::LeftStickX), -pad.value(Axis::LeftStickY));
+        let mut input = Input {
+            left: pad.is_pressed(Button::DPadLeft),
+            right: pad.is_pressed(Button::DPadRight),
+            up: pad.is_pressed(Button::DPadUp),
+            down: pad.is_pressed(Button::DPadDown),
+            fire: pad.is_pressed(Button::South) || pad.is_pressed(Button::RightTrigger2),
+            bomb: pad.is_pressed(Button::East),
+            pause: self.start[player],
+            ..Default::default()
+        };
+        input.set_stick(apply_deadzone(stick));
+        input
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Gamepads {
+    pub fn open() -> Self {
+        Gamepads {
+            menu: MenuInput::default(),
+        }
+    }
+
+    pub fn update(&mut self) -> Vec<PadEvent> {
+        vec![]
+    }
+
+    pub fn input(&self, _player: usize) -> Input {
+        Input::default()
+    }
+}
+
+// Ignore small tilts around the center and rescale the rest, so the ship can
+// still creep along just past the deadzone
+fn apply_deadzone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length < DEADZONE {
+        return Vec2::ZERO;
+    }
+    stick / length * ((length - DEADZONE) / (1.0 - DEADZONE)).min(1.0)
+}
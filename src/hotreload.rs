@@ -0,0 +1,104 @@
+use macroquad::prelude::*;
+use std::time::SystemTime;
+
+// Seconds between looks at the files
+const POLL_INTERVAL: f64 = 0.5;
+
+// Shader sources that debug builds watch in `src/`, so edits show up without
+// a rebuild. Release, web and Android builds only have the embedded copies.
+pub struct ShaderWatcher {
+    files: Vec<(&'static str, Option<SystemTime>)>,
+    next_poll: f64,
+    // The last compile error of each file, until it builds again
+    errors: Vec<(&'static str, String)>,
+}
+
+impl ShaderWatcher {
+    pub fn new(files: &[&'static str]) -> Self {
+        ShaderWatcher {
+            files: files.iter().map(|file| (*file, modified(file))).collect(),
+            next_poll: 0.0,
+            errors: vec![],
+        }
+    }
+
+    // The files edited since the last call, with their new source
+    pub fn poll(&mut self) -> Vec<(&'static str, String)> {
+        if get_time() < self.next_poll {
+            return vec![];
+        }
+        self.next_poll = get_time() + POLL_INTERVAL;
+
+        let mut changed = vec![];
+        for (file, last_modified) in &mut self.files {
+            let now = modified(file);
+            if now.is_none() || now == *last_modified {
+                continue;
+            }
+            *last_modified = now;
+            match read(file) {
+                Some(source) => changed.push((*file, source)),
+                None => warn!("Could not read {}", file),
+            }
+        }
+        changed
+    }
+
+    // Keep the error from recompiling `file` on screen, or clear it
+    pub fn report(&mut self, file: &'static str, result: Result<(), macroquad::Error>) {
+        self.errors.retain(|(other, _)| *other != file);
+        match result {
+            Ok(()) => info!("Reloaded {}", file),
+            Err(err) => {
+                let message = match err {
+                    macroquad::Error::ShaderError(ShaderError::CompilationError { error_message, .. }) => {
+                        error_message
+                    }
+                    macroquad::Error::ShaderError(ShaderError::LinkError(message)) => message,
+                    err => err.to_string(),
+                };
+                error!("{}: {}", file, message);
+                self.errors.push((file, message));
+            }
+        }
+    }
+
+    // Compile errors over everything else, in window coordinates after the
+    // canvas is presented. The old shader keeps running.
+    pub fn draw_errors(&self) {
+        let mut y = 20.0;
+        for (file, message) in &self.errors {
+            for line in std::iter::once(format!("{} failed to compile:", file))
+                .chain(message.lines().map(str::to_string))
+            {
+                draw_text(&line, 10.0, y, 20.0, RED);
+                y += 20.0;
+            }
+        }
+    }
+}
+
+#[cfg(all(debug_assertions, not(any(target_arch = "wasm32", target_os = "android"))))]
+fn path(file: &str) -> String {
+    format!("{}/src/{}", env!("CARGO_MANIFEST_DIR"), file)
+}
+
+#[cfg(all(debug_assertions, not(any(target_arch = "wasm32", target_os = "android"))))]
+fn modified(file: &str) -> Option<SystemTime> {
+    std::fs::metadata(path(file)).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(all(debug_assertions, not(any(target_arch = "wasm32", target_os = "android"))))]
+fn read(file: &str) -> Option<String> {
+    std::fs::read_to_string(path(file)).ok()
+}
+
+#[cfg(not(all(debug_assertions, not(any(target_arch = "wasm32", target_os = "android")))))]
+fn modified(_file: &str) -> Option<SystemTime> {
+    None
+}
+
+#[cfg(not(all(debug_assertions, not(any(target_arch = "wasm32", target_os = "android")))))]
+fn read(_file: &str) -> Option<String> {
+    None
+}
@@ -0,0 +1,79 @@
+use crate::bindings::{Action, Bindings};
+use crate::gamepad::Gamepads;
+use crate::player::MAX_PLAYERS;
+use crate::touch::TouchControls;
+use macroquad::prelude::*;
+
+// Snapshot of the player's controls for a single simulation step
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub fire: bool,
+    // Secondary fire, drops a bomb
+    pub bomb: bool,
+    pub pause: bool,
+    // Analog stick, -127 to 127 on each axis so replays store it exactly
+    pub stick: [i8; 2],
+}
+
+impl Input {
+    // The keys bound for player `player`
+    pub fn from_keyboard(bindings: &Bindings, player: usize) -> Self {
+        Input {
+            left: bindings.is_down(player, Action::MoveLeft),
+            right: bindings.is_down(player, Action::MoveRight),
+            up: bindings.is_down(player, Action::MoveUp),
+            down: bindings.is_down(player, Action::MoveDown),
+            fire: bindings.is_down(player, Action::Fire),
+            bomb: bindings.is_down(player, Action::Bomb),
+            // Escape pauses too, whatever is bound
+            pause: bindings.is_pressed(player, Action::Pause) || player == 0 && is_key_pressed(KeyCode::Escape),
+            stick: [0, 0],
+        }
+    }
+
+    pub fn stick(&self) -> Vec2 {
+        vec2(self.stick[0] as f32, self.stick[1] as f32) / 127.0
+    }
+
+    pub fn set_stick(&mut self, stick: Vec2) {
+        let stick = stick.clamp_length_max(1.0) * 127.0;
+        self.stick = [stick.x.round() as i8, stick.y.round() as i8];
+    }
+
+    // Controls held on either device, steering with whichever stick is
+    // pushed further
+    pub fn merge(self, other: Input) -> Input {
+        Input {
+            left: self.left || other.left,
+            right: self.right || other.right,
+            up: self.up || other.up,
+            down: self.down || other.down,
+            fire: self.fire || other.fire,
+            bomb: self.bomb || other.bomb,
+            pause: self.pause || other.pause,
+            stick: if other.stick().length() > self.stick().length() {
+                other.stick
+            } else {
+                self.stick
+            },
+        }
+    }
+}
+
+// Every player's controls for the same step, from the keyboard and their
+// controller, with the touch screen, and the mouse if `mouse` is on, driving
+// the first player
+pub fn read_all(bindings: &Bindings, gamepads: &Gamepads, touch: &TouchControls, mouse: bool) -> [Input; MAX_PLAYERS] {
+    let mut inputs: [Input; MAX_PLAYERS] = std::array::from_fn(|player| {
+        Input::from_keyboard(bindings, player).merge(gamepads.input(player))
+    });
+    inputs[0] = inputs[0].merge(touch.input());
+    if mouse {
+        inputs[0].fire |= is_mouse_button_down(MouseButton::Left);
+    }
+    inputs
+}
@@ -0,0 +1,384 @@
+use crate::data::GameData;
+use crate::difficulty::Director;
+use crate::enemy;
+use crate::rng::Rng;
+use serde::Deserialize;
+
+pub const LEVELS_DIR: &str = "levels";
+// Lists the level files to play in order
+pub const LEVEL_INDEX: &str = "levels/index.ron";
+
+// Average number of enemies spawned per second once the stages run out. The
+// original loop spawned on 4 of every 99 frames at 60 fps.
+const ENDLESS_SPAWN_RATE: f32 = 60.0 * 4.0 / 99.0;
+
+// How the members of a wave are arranged when they enter
+#[derive(Clone, Debug, Deserialize)]
+pub enum Formation {
+    // Side by side, `spacing` pixels apart
+    Line { spacing: f32 },
+    // A V pointing down with its tip at the wave's `x`
+    V { spacing: f32 },
+    // One behind the other
+    Column { spacing: f32 },
+    // Anywhere across the screen
+    Random,
+}
+
+// How an enemy moves after entering the screen
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub enum Path {
+    #[default]
+    Straight,
+    // Weave side to side around the entry point
+    Sine { amplitude: f32, frequency: f32 },
+    // Glide across to `to` (a fraction of the screen width) while descending
+    Swoop { to: f32 },
+    // Stop at `at` (a fraction of the screen height) for `time` seconds
+    Hover { at: f32, time: f32 },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Wave {
+    // Seconds after the previous wave finished spawning
+    #[serde(default)]
+    pub delay: f32,
+    // Archetype name from enemies.ron
+    pub enemy: String,
+    #[serde(default = "default_count")]
+    pub count: u32,
+    #[serde(default = "default_formation")]
+    pub formation: Formation,
+    #[serde(default)]
+    pub path: Path,
+    // Center of the formation as a fraction of the screen width
+    #[serde(default = "default_x")]
+    pub x: f32,
+    // Overrides the archetype's speed range
+    #[serde(default)]
+    pub speed: Option<f32>,
+    // Seconds between members entering, 0 for all at once
+    #[serde(default)]
+    pub interval: f32,
+    // Hold the wave back until every enemy on screen is gone
+    #[serde(default)]
+    pub wait_for_clear: bool,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_formation() -> Formation {
+    Formation::Random
+}
+
+fn default_x() -> f32 {
+    0.5
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub waves: Vec<Wave>,
+    // Boss name from bosses.ron, fought after the last wave
+    #[serde(default)]
+    pub boss: Option<String>,
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Level, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+}
+
+// An enemy the sequencer wants in the world this tick
+pub struct SpawnOrder {
+    pub kind: usize,
+    // None for a random position across the screen
+    pub x: Option<f32>,
+    // Added to the spawn height, negative is further above the screen
+    pub y_offset: f32,
+    pub speed: Option<f32>,
+    pub path: Path,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StageEvent {
+    Started(usize),
+    Cleared(usize),
+    // Index into the boss definitions
+    BossIncoming(usize),
+    EndlessStarted,
+}
+
+enum Phase {
+    // Counting down the delay before the next wave
+    Waiting { timer: f32 },
+    // Letting the members of a wave in
+    Spawning { spawned: u32, timer: f32 },
+    // Every wave is out, waiting for the screen to clear
+    Finishing,
+}
+
+// Walks through the levels' waves, then falls back to random endless spawning
+pub struct Sequencer {
+    // None once the stages are over
+    level: Option<usize>,
+    wave: usize,
+    phase: Phase,
+    announced: bool,
+    boss_sent: bool,
+    // Enemies wanted in the world, collected with `take_spawns`
+    spawns: Vec<SpawnOrder>,
+}
+
+impl Sequencer {
+    pub fn new(data: &GameData) -> Self {
+        Sequencer {
+            level: (!data.levels.is_empty()).then_some(0),
+            wave: 0,
+            phase: Phase::Waiting { timer: 0.0 },
+            announced: false,
+            boss_sent: false,
+            spawns: vec![],
+        }
+    }
+
+    pub fn take_spawns(&mut self) -> Vec<SpawnOrder> {
+        std::mem::take(&mut self.spawns)
+    }
+
+    pub fn update(
+        &mut self,
+        dt: f32,
+        data: &GameData,
+        width: f32,
+        field_empty: bool,
+        director: &Director,
+        rng: &mut Rng,
+    ) -> Option<StageEvent> {
+        let Some(level_index) = self.level else {
+            if !self.announced {
+                self.announced = true;
+                return Some(StageEvent::EndlessStarted);
+            }
+            // Endless: random archetypes like the original game, ramped up by the director
+            if rng.gen_range(0.0, 1.0) < ENDLESS_SPAWN_RATE * director.spawn_rate * dt {
+                if let Some(kind) = enemy::choose_archetype(&data.enemies, director.intensity, rng) {
+                    self.spawns.push(SpawnOrder {
+                        kind,
+                        x: None,
+                        y_offset: 0.0,
+                        speed: None,
+                        path: Path::Straight,
+                    });
+                }
+            }
+            return None;
+        };
+
+        if !self.announced {
+            self.announced = true;
+            return Some(StageEvent::Started(level_index));
+        }
+
+        let level = &data.levels[level_index];
+        match &mut self.phase {
+            Phase::Waiting { timer } => {
+                let Some(wave) = level.waves.get(self.wave) else {
+                    self.phase = Phase::Finishing;
+                    return None;
+                };
+                if wave.wait_for_clear && !field_empty {
+                    return None;
+                }
+                *timer += dt * director.spawn_rate;
+                if *timer >= wave.delay {
+                    self.phase = Phase::Spawning {
+                        spawned: 0,
+                        timer: 0.0,
+                    };
+                }
+            }
+            Phase::Spawning { spawned, timer } => {
+                let wave = &level.waves[self.wave];
+                *timer -= dt * director.spawn_rate;
+                while *spawned < wave.count && *timer <= 0.0 {
+                    self.spawns.push(wave_member(wave, *spawned, data, width));
+                    *spawned += 1;
+                    *timer += wave.interval;
+                }
+                if *spawned >= wave.count {
+                    self.wave += 1;
+                    self.phase = if self.wave < level.waves.len() {
+                        Phase::Waiting { timer: 0.0 }
+                    } else {
+                        Phase::Finishing
+                    };
+                }
+            }
+            Phase::Finishing => {
+                if field_empty && !self.boss_sent {
+                    self.boss_sent = true;
+                    if let Some(boss) = level.boss.as_deref().and_then(|name| data.boss_index(name)) {
+                        return Some(StageEvent::BossIncoming(boss));
+                    }
+                }
+                if field_empty {
+                    let next = level_index + 1;
+                    self.level = (next < data.levels.len()).then_some(next);
+                    self.wave = 0;
+                    self.phase = Phase::Waiting { timer: 0.0 };
+                    self.announced = false;
+                    self.boss_sent = false;
+                    return Some(StageEvent::Cleared(level_index));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Where the `index`th member of a wave enters
+fn wave_member(wave: &Wave, index: u32, data: &GameData, width: f32) -> SpawnOrder {
+    let center = wave.x * width;
+    let offset = index as f32 - (wave.count as f32 - 1.0) / 2.0;
+    let (x, y_offset) = match wave.formation {
+        Formation::Line { spacing } => (Some(center + offset * spacing), 0.0),
+        Formation::V { spacing } => (Some(center + offset * spacing), -offset.abs() * spacing * 0.6),
+        Formation::Column { spacing } => (Some(center), -(index as f32) * spacing),
+        Formation::Random => (None, 0.0),
+    };
+    SpawnOrder {
+        kind: data.archetype_index(&wave.enemy).unwrap_or(0),
+        x,
+        y_offset,
+        speed: wave.speed,
+        path: wave.path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+    use std::rc::Rc;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    // The bundled enemies and bosses with `levels` in place of the stages
+    fn data(levels: &[&str]) -> GameData {
+        let mut data = Rc::try_unwrap(data::bundled()).ok().unwrap();
+        data.levels = levels.iter().map(|text| Level::parse(text).unwrap()).collect();
+        data
+    }
+
+    fn director() -> Director {
+        Director {
+            intensity: 1.0,
+            spawn_rate: 1.0,
+            speed: 1.0,
+        }
+    }
+
+    // Step for `seconds`, returning the events and the spawns with the time
+    // they came in
+    fn run(
+        sequencer: &mut Sequencer,
+        data: &GameData,
+        seconds: f32,
+        field_empty: bool,
+    ) -> (Vec<StageEvent>, Vec<(f32, SpawnOrder)>) {
+        let mut rng = Rng::new(1);
+        let (mut events, mut spawns) = (vec![], vec![]);
+        for tick in 0..(seconds / DT).round() as usize {
+            events.extend(sequencer.update(DT, data, 800.0, field_empty, &director(), &mut rng));
+            let time = (tick + 1) as f32 * DT;
+            spawns.extend(sequencer.take_spawns().into_iter().map(|order| (time, order)));
+        }
+        (events, spawns)
+    }
+
+    #[test]
+    fn waves_come_in_order_and_on_time() {
+        let data = data(&[r#"(
+            name: "Test",
+            waves: [
+                (delay: 1.0, enemy: "small", count: 3, formation: Line(spacing: 50.0)),
+                (delay: 0.5, enemy: "medium", count: 2, formation: Column(spacing: 40.0), interval: 0.25),
+            ],
+        )"#]);
+        let mut sequencer = Sequencer::new(&data);
+        let (events, spawns) = run(&mut sequencer, &data, 3.0, false);
+        assert_eq!(events, vec![StageEvent::Started(0)]);
+
+        let small = data.archetype_index("small").unwrap();
+        let medium = data.archetype_index("medium").unwrap();
+        assert_eq!(spawns.len(), 5);
+        for (time, order) in &spawns[..3] {
+            assert_eq!(order.kind, small);
+            assert!((time - 1.0).abs() < 0.05, "{}", time);
+        }
+        let xs: Vec<_> = spawns[..3].iter().map(|(_, order)| order.x.unwrap()).collect();
+        assert_eq!(xs, vec![350.0, 400.0, 450.0]);
+
+        let (first, second) = (&spawns[3], &spawns[4]);
+        assert_eq!((first.1.kind, second.1.kind), (medium, medium));
+        assert!((first.0 - 1.5).abs() < 0.05, "{}", first.0);
+        assert!((second.0 - 1.75).abs() < 0.05, "{}", second.0);
+        assert_eq!(first.1.x, second.1.x);
+        assert_eq!(second.1.y_offset, -40.0);
+    }
+
+    #[test]
+    fn wave_waits_for_a_clear_screen() {
+        let data = data(&[r#"(
+            name: "Test",
+            waves: [(delay: 0.0, enemy: "small", wait_for_clear: true)],
+        )"#]);
+        let mut sequencer = Sequencer::new(&data);
+        let (_, spawns) = run(&mut sequencer, &data, 2.0, false);
+        assert!(spawns.is_empty());
+        let (_, spawns) = run(&mut sequencer, &data, 0.1, true);
+        assert_eq!(spawns.len(), 1);
+    }
+
+    #[test]
+    fn stages_end_with_the_boss_then_go_endless() {
+        let boss = data::bundled().bosses[0].name.clone();
+        let first = format!(r#"(name: "One", waves: [(enemy: "small")], boss: Some("{}"))"#, boss);
+        let data = data(&[&first, r#"(name: "Two", waves: [(delay: 1.0, enemy: "small")])"#]);
+        let mut sequencer = Sequencer::new(&data);
+
+        // Enemies still about hold the stage open
+        let (events, spawns) = run(&mut sequencer, &data, 1.0, false);
+        assert_eq!(events, vec![StageEvent::Started(0)]);
+        assert_eq!(spawns.len(), 1);
+
+        let (events, _) = run(&mut sequencer, &data, 0.1, true);
+        assert_eq!(
+            events,
+            vec![
+                StageEvent::BossIncoming(data.boss_index(&boss).unwrap()),
+                StageEvent::Cleared(0),
+                StageEvent::Started(1),
+            ]
+        );
+
+        let (events, _) = run(&mut sequencer, &data, 1.5, true);
+        assert_eq!(events, vec![StageEvent::Cleared(1), StageEvent::EndlessStarted]);
+    }
+
+    #[test]
+    fn endless_spawns_at_the_original_rate() {
+        let data = data(&[]);
+        let mut sequencer = Sequencer::new(&data);
+        let seconds = 200.0;
+        let (events, spawns) = run(&mut sequencer, &data, seconds, false);
+        assert_eq!(events, vec![StageEvent::EndlessStarted]);
+        let rate = spawns.len() as f32 / seconds;
+        assert!((rate - ENDLESS_SPAWN_RATE).abs() < ENDLESS_SPAWN_RATE * 0.15, "{}", rate);
+        assert!(spawns.iter().all(|(_, order)| order.x.is_none()));
+    }
+}
@@ -1,4 +1,6 @@
 const FRAGMENT_SHADER: &str = include_str!("starfield-shader.glsl");
+// The same shader on disk, for hot reloading
+const FRAGMENT_SHADER_FILE: &str = "starfield-shader.glsl";
 const VERTEX_SHADER: &str = "#version 100
 attribute vec3 position;
 attribute vec2 texcoord;
@@ -16,20 +18,74 @@ void main() {
 ";
 
 mod assets;
+mod bindings;
+mod camera;
+mod boss;
+mod canvas;
+mod combo;
+mod data;
+mod difficulty;
+mod enemy;
+mod gamepad;
+mod hotreload;
+mod input;
+mod level;
+mod pattern;
+mod player;
+mod pointer;
+mod postfx;
+mod powerup;
+mod projectile;
+mod render;
+mod replay;
+mod rng;
+mod save;
+mod settings;
 mod shape;
+mod storage;
+mod timestep;
+mod touch;
+mod weapon;
+mod world;
 
-use std::fs;
 use assets::Resources;
-use shape::Shape;
+use boss::BossState;
+use bindings::{Action, Bindings, BindingsMenu};
+use camera::GameCamera;
+use canvas::Canvas;
+use gamepad::{Gamepads, PadEvent};
+use render::Sprites;
+use replay::{Replay, ReplayPlayer, LAST_REPLAY_FILE};
+use save::{SaveData, ScoreEntry, MAX_NAME_LEN};
+use settings::SettingsMenu;
+use timestep::{FixedTimestep, TICK};
+use touch::TouchControls;
+use pointer::Pointer;
+use level::StageEvent;
+use hotreload::ShaderWatcher;
+use postfx::{Pass, PostProcess};
+use settings::Setting;
+use world::{World, WorldEvent};
 use macroquad::{
     prelude::*,
     ui::{hash, root_ui},
-    experimental::animation::{AnimatedSprite, Animation},
     audio::{play_sound, play_sound_once, set_sound_volume, stop_sound, PlaySoundParams},
-    experimental::collections::storage,
+    experimental::collections,
 };
 use macroquad_particles::{self as particles, AtlasConfig, Emitter, EmitterConfig};
-use rand::ChooseRandom;
+
+// Entries of the main menu the D-pad moves between: Play, Replay, Controls,
+// Settings, Quit, then the difficulty and player count pickers
+const MENU_ITEMS: usize = 7;
+// Trauma and freezes for the camera, by how big the hit is
+const EXPLOSION_TRAUMA: f32 = 0.3;
+const DEATH_TRAUMA: f32 = 0.7;
+const BOMB_TRAUMA: f32 = 0.6;
+const BOSS_TRAUMA: f32 = 0.9;
+// Enemies at least this big freeze the action when they blow up
+const BIG_KILL_SIZE: f32 = 48.0;
+const BIG_KILL_STOP: f32 = 0.06;
+const BOSS_KILL_STOP: f32 = 0.25;
 
 #[derive(Debug)]
 struct ScreenCenter {
@@ -39,11 +95,27 @@ struct ScreenCenter {
 
 enum GameState {
     MainMenu,
+    Controls,
+    Settings,
     Playing,
     Paused,
     GameOver,
 }
 
+fn starfield_material(fragment: &str) -> Result<Material, macroquad::Error> {
+    load_material(
+        ShaderSource::Glsl { vertex: VERTEX_SHADER, fragment },
+        MaterialParams {
+            uniforms: vec![
+                UniformDesc::new("iResolution", UniformType::Float2),
+                UniformDesc::new("direction_modifier", UniformType::Float1),
+                UniformDesc::new("num_layers", UniformType::Float1),
+            ],
+            ..Default::default()
+        },
+    )
+}
+
 fn particle_explosion() -> particles::EmitterConfig {
     particles::EmitterConfig {
         local_coords: false,
@@ -62,10 +134,31 @@ fn particle_explosion() -> particles::EmitterConfig {
     }
 }
 
+// Small burst of sparks where a bullet chips a tougher enemy
+fn particle_debris() -> particles::EmitterConfig {
+    particles::EmitterConfig {
+        local_coords: false,
+        one_shot: true,
+        emitting: true,
+        amount: 6,
+        lifetime: 0.3,
+        lifetime_randomness: 0.4,
+        explosiveness: 1.0,
+        initial_direction: vec2(0.0, -1.0),
+        initial_direction_spread: std::f32::consts::PI / 2.0,
+        initial_velocity: 180.0,
+        initial_velocity_randomness: 0.6,
+        size: 4.0,
+        size_randomness: 0.5,
+        atlas: Some(AtlasConfig::new(5, 1, 0..)),
+        ..Default::default()
+    }
+}
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "Agical Macroquad Tutorial".to_owned(),
-        // window_resizable: false,
+        window_resizable: true,
         ..Default::default()
     }
 }
@@ -75,168 +168,89 @@ async fn main() -> Result<(), macroquad::Error> {
     // Setting the asset folder
     set_pc_assets_folder("assets");
     Resources::load().await?;
-    let resources = storage::get::<Resources>();
+    let resources = collections::storage::get::<Resources>();
 
     // seeding the RNG
     rand::srand(miniquad::date::now() as u64);
 
-    // Setting shapes
-    let mut squares = vec![];
-    let mut bullets: Vec<Shape> = vec![];
+    let storage = storage::platform_storage();
+    let mut save_data = SaveData::load(storage.as_ref());
+    let mut bindings = Bindings::load(storage.as_ref());
+    let mut bindings_menu = BindingsMenu::default();
+    let mut settings_menu = SettingsMenu::default();
+    // Rank of the run's leaderboard entry, saved under the last name used,
+    // and the name being typed for it
+    let mut pending_entry: Option<(usize, String)> = None;
+    let mut world = World::new(
+        resources.data.clone(),
+        canvas::WIDTH,
+        canvas::HEIGHT,
+        save_data.high_score(),
+        0,
+        resources
+            .data
+            .difficulty_index(&save_data.difficulty)
+            .or_else(|| resources.data.difficulty_index(difficulty::DEFAULT_DIFFICULTY))
+            .unwrap_or(0),
+        1,
+    );
+    // The menu's picks, restored after watching a replay on another preset
+    let mut chosen_difficulty = world.difficulty;
+    let mut chosen_players = 1;
+    // Menu entry picked with the D-pad
+    let mut menu_focus = 0;
     let mut explosions: Vec<(Emitter, Vec2)> = vec![];
-    let mut circle = Shape {
-        size: 32.0,
-        speed: SPEED,
-        x: screen_width() / 2.0,
-        y: screen_height() / 2.0,
-        color: Color::from_hex(0xb1de78),
-        collided: false,
-    };
-
-    let mut pc_last_dir_change: f32 = 0.0;
+    let mut timestep = FixedTimestep::default();
 
     let mut game_state = GameState::MainMenu;
 
-    // "Player entity"
-    const SPEED: f32 = 200.0;
-
-    let enemy_clr: Vec<Color> = vec![
-        Color::from_hex(0xfca78b),
-        Color::from_hex(0xf6957d),
-        Color::from_hex(0xf0826e),
-        Color::from_hex(0xea7060),
-        Color::from_hex(0xe45d51),
-        Color::from_hex(0xde4b43),
-        Color::from_hex(0xd83834),
-    ];
-
-    let mut last_shot: f64 = 0.0;
-
-    let mut score: u32 = 0;
-    let mut high_score: u32 = fs::read_to_string("highscore.dat")
-        .map_or(Ok(0), |i| i.parse::<u32>())
-        .unwrap_or(0);
+    // The run being recorded, or the replay being played back instead of
+    // reading the keyboard
+    let mut recording = Replay::default();
+    let mut playback: Option<ReplayPlayer> = None;
+    let mut pending_replay: Option<Replay> = None;
+    let mut stored_high_score = 0;
+
+    // Stage announcement and when it stops showing
+    let mut banner: Option<(String, f64)> = None;
+
+    let mut gamepads = Gamepads::open();
+    let mut touch = TouchControls::default();
+    let mut pointer = Pointer::default();
+    // Player whose controller was unplugged mid-game, shown while paused
+    let mut disconnected: Option<usize> = None;
+
+    // `--replay <file>` plays a recorded run straight away
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(path) = args.iter().position(|arg| arg == "--replay").and_then(|i| args.get(i + 1)) {
+        match Replay::load(storage.as_ref(), path) {
+            Ok(replay) => pending_replay = Some(replay),
+            Err(err) => error!("{}: {}", path, err),
+        }
+    }
 
     // Starfield shader setup
     let mut direction_modifier: f32 = 0.0;
+    let mut num_layers: f32 = 4.0;
     let render_target = render_target(320,150);
     render_target.texture.set_filter(FilterMode::Nearest);
 
-    let material = load_material(
-        ShaderSource::Glsl { vertex: VERTEX_SHADER, fragment: FRAGMENT_SHADER },
-        MaterialParams {
-            uniforms: vec![
-                UniformDesc::new("iResolution", UniformType::Float2),
-                UniformDesc::new("direction_modifier", UniformType::Float1),
-            ],
-            ..Default::default()
-        },
-    )?;
-
-    // Setup bullet sprite
-    let mut bullet_sprite = AnimatedSprite::new(
-        16,
-        16,
-        &[
-            Animation {
-                name: "bullet".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            },
-            Animation {
-                name: "bolt".to_string(),
-                row: 1,
-                frames: 2,
-                fps: 12,
-            },
-        ],
-        true,
-    );
-    bullet_sprite.set_animation(1);
-
-    // Setup ship sprite
-    let mut ship_sprite = AnimatedSprite::new(
-        16,
-        24,
-        &[
-            Animation {
-                name: "idle".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            },
-            Animation {
-                name: "slight_left".to_string(),
-                row: 1,
-                frames: 2,
-                fps: 12,
-            },
-            Animation {
-                name: "left".to_string(),
-                row: 2,
-                frames: 2,
-                fps: 12,
-            },
-            Animation {
-                name: "slight_right".to_string(),
-                row: 3,
-                frames: 2,
-                fps: 12,
-            },
-            Animation {
-                name: "right".to_string(),
-                row: 4,
-                frames: 2,
-                fps: 12,
-            },
-        ],
-        true,
-    );
+    let mut material = starfield_material(FRAGMENT_SHADER)?;
 
-    // Setup enemy sprites
-    let mut enemy_sm_sprite = AnimatedSprite::new(
-        17,
-        16,
-        &[
-            Animation {
-                name: "enemy_small".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            },
-        ],
-        true,
-    );
-    let mut enemy_md_sprite = AnimatedSprite::new(
-        32,
-        16,
-        &[
-            Animation {
-                name: "enemy_med".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            },
-        ],
-        true,
-    );
-    let mut enemy_lg_sprite = AnimatedSprite::new(
-        32,
-        32,
-        &[
-            Animation {
-                name: "enemy_big".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            },
-        ],
-        true,
+    let mut sprites = Sprites::new(&resources.data)?;
+    let mut canvas = Canvas::new(save_data.integer_scaling);
+    let mut camera = GameCamera::default();
+    let mut postfx = PostProcess::new()?;
+    let mut shader_watcher = ShaderWatcher::new(
+        &std::iter::once(FRAGMENT_SHADER_FILE)
+            .chain(Pass::ALL.iter().map(|pass| pass.file()))
+            .collect::<Vec<_>>(),
     );
+    // Uniform sliders for tweaking the starfield, in debug builds
+    let mut show_shader_panel = false;
 
     root_ui().push_skin(&resources.ui_skin);
-    let window_size = vec2(370., 320.);
+    let window_size = vec2(370., 600.);
 
     // Set individual sound volume
     set_sound_volume(&resources.sound_explosion, 0.25);
@@ -252,300 +266,492 @@ async fn main() -> Result<(), macroquad::Error> {
     );
 
     loop {
+        for (file, source) in shader_watcher.poll() {
+            let result = if file == FRAGMENT_SHADER_FILE {
+                starfield_material(&source).map(|reloaded| material = reloaded)
+            } else {
+                postfx.reload(file, &source)
+            };
+            shader_watcher.report(file, result);
+        }
+
+        canvas.begin();
         clear_background(BLACK);
 
         // Draw Starfield
-        material.set_uniform("iResolution", (screen_width(), screen_height()));
+        material.set_uniform("iResolution", (canvas::WIDTH, canvas::HEIGHT));
         material.set_uniform("direction_modifier", direction_modifier);
+        material.set_uniform("num_layers", num_layers);
         gl_use_material(&material);
         draw_texture_ex(
             &render_target.texture, 
             0., 
             0., WHITE, 
             DrawTextureParams{
-                dest_size: Some(vec2(screen_width(), screen_height())),
+                dest_size: Some(vec2(canvas::WIDTH, canvas::HEIGHT)),
                 ..Default::default()
             },
         );
         gl_use_default_material();
 
         let screen_center = ScreenCenter {
-            x: screen_width() / 2.0,
-            y: screen_height() / 2.0,
+            x: canvas::WIDTH / 2.0,
+            y: canvas::HEIGHT / 2.0,
         };
 
         // println!("Screen center: {:?}", screen_center);
+
+        touch.update(&canvas);
+        for event in gamepads.update() {
+            match event {
+                PadEvent::Connected(player) => {
+                    info!("Controller connected for player {}", player + 1);
+                    banner = Some((format!("P{} controller connected", player + 1), get_time() + 1.5));
+                    if disconnected == Some(player) {
+                        disconnected = None;
+                    }
+                }
+                PadEvent::Disconnected(player) => {
+                    warn!("Controller for player {} disconnected", player + 1);
+                    // Stop before the ship drifts into something
+                    if matches!(game_state, GameState::Playing)
+                        && playback.is_none()
+                        && player < world.players.len()
+                    {
+                        disconnected = Some(player);
+                        game_state = GameState::Paused;
+                    }
+                }
+            }
+        }
         
+        // Re-simulate a recorded run with the same seed and playfield
+        if let Some(replay) = pending_replay.take() {
+            world.width = replay.width;
+            world.height = replay.height;
+            stored_high_score = world.high_score;
+            match world.data.difficulty_index(&replay.difficulty) {
+                Some(index) => world.difficulty = index,
+                None => warn!("Replay uses unknown difficulty {}", replay.difficulty),
+            }
+            world.reset(replay.seed, replay.players);
+            playback = Some(ReplayPlayer::new(replay));
+            timestep.reset();
+            explosions.clear();
+            sprites.clear_popups();
+            camera.reset();
+            banner = None;
+            game_state = GameState::Playing;
+        }
+
         match game_state {
             GameState::MainMenu => {
+                let menu = gamepads.menu;
+                if menu.up {
+                    menu_focus = (menu_focus + MENU_ITEMS - 1) % MENU_ITEMS;
+                }
+                if menu.down {
+                    menu_focus = (menu_focus + 1) % MENU_ITEMS;
+                }
+                let chosen = |item: usize| menu.confirm && menu_focus == item;
+
+                // The UI is drawn over the window rather than on the canvas
                 root_ui().window(
                     hash!(),
                     vec2(
-                        screen_center.x - window_size.x / 2.0,
-                        screen_center.y - window_size.y / 2.0,
+                        screen_width() / 2.0 - window_size.x / 2.0,
+                        screen_height() / 2.0 - window_size.y / 2.0,
                     ),
                     window_size,
                     |ui| {
                         ui.label(vec2(80., -34.), "Main Menu");
-                        if ui.button(vec2(65., 25.), "Play") {
-                            squares.clear();
-                            bullets.clear();
+                        // Mark the entry the D-pad is on
+                        let marker = [25., 105., 185., 265., 345., 425., 505.][menu_focus];
+                        ui.label(vec2(if menu_focus < 5 { 30. } else { 0. }, marker + 20.), ">");
+
+                        if ui.button(vec2(65., 25.), "Play") || chosen(0) {
+                            let seed = miniquad::date::now() as u64;
+                            world.width = canvas::WIDTH;
+                            world.height = canvas::HEIGHT;
+                            world.difficulty = chosen_difficulty;
+                            world.reset(seed, chosen_players);
+                            recording = Replay::new(
+                                seed,
+                                world.width,
+                                world.height,
+                                &world.preset().name,
+                                chosen_players,
+                            );
+                            playback = None;
+                            timestep.reset();
                             explosions.clear();
-                            circle.x = screen_center.x;
-                            circle.y = screen_center.y;
-                            score = 0;
+                            sprites.clear_popups();
+                            camera.reset();
+                            banner = None;
                             game_state = GameState::Playing;
                             set_sound_volume(&resources.theme_music, 1.);
                         }
 
-                        if ui.button(vec2(65., 125.), "Quit") {
-                            std::process::exit(0);
+                        if ui.button(vec2(65., 105.), "Replay") || chosen(1) {
+                            match Replay::load(storage.as_ref(), LAST_REPLAY_FILE) {
+                                Ok(replay) => pending_replay = Some(replay),
+                                Err(err) => error!("{}: {}", LAST_REPLAY_FILE, err),
+                            }
                         }
-                    },
-                );
-            }
-            GameState::Playing => {
-                // Get delta time
-                let delta_time = get_frame_time();
 
-                // --- Player ---
-                ship_sprite.set_animation(0);
+                        if ui.button(vec2(65., 185.), "Controls") || chosen(2) {
+                            bindings_menu = BindingsMenu::default();
+                            game_state = GameState::Controls;
+                        }
 
-                // Input setup
-                // Check for direction change time
-                if is_key_released(KeyCode::Right) || is_key_released(KeyCode::Left) {
-                    pc_last_dir_change = 0.0;
-                }
+                        if ui.button(vec2(65., 265.), "Settings") || chosen(3) {
+                            settings_menu = SettingsMenu::default();
+                            game_state = GameState::Settings;
+                        }
 
-                if is_key_down(KeyCode::Right) {
-                    circle.x += circle.speed * delta_time;
-                    direction_modifier += 0.05 * delta_time;
+                        if ui.button(vec2(65., 345.), "Quit") || chosen(4) {
+                            std::process::exit(0);
+                        }
 
-                    // Adapting animation to direction change length
-                    if pc_last_dir_change == 0.0 {
-                        pc_last_dir_change = get_time() as f32;
-                    }
-                    if pc_last_dir_change > 0.0 && get_time() as f32 - pc_last_dir_change > 0.2 {
-                        ship_sprite.set_animation(4);
-                    }
-                    else {
-                        ship_sprite.set_animation(3);
-                    }
+                        // Cycle through the difficulty presets
+                        let presets = &resources.data.difficulties;
+                        let mut picked = None;
+                        if ui.button(vec2(20., 425.), "<") || (menu_focus == 5 && menu.left) {
+                            picked = Some((chosen_difficulty + presets.len() - 1) % presets.len());
+                        }
+                        if ui.button(vec2(270., 425.), ">") || (menu_focus == 5 && menu.right) {
+                            picked = Some((chosen_difficulty + 1) % presets.len());
+                        }
+                        ui.label(vec2(110., 445.), &presets[chosen_difficulty].name);
+                        if let Some(index) = picked {
+                            chosen_difficulty = index;
+                            save_data.difficulty = presets[index].name.clone();
+                            if let Err(err) = save_data.save(storage.as_ref()) {
+                                error!("Could not save settings: {}", err);
+                            }
+                        }
 
-                }
-                if is_key_down(KeyCode::Left) {
-                    circle.x -= circle.speed * delta_time;
-                    direction_modifier -= 0.05 * delta_time;
-                    
-                    // Adapting animation to direction change length
-                    if pc_last_dir_change == 0.0 {
-                        pc_last_dir_change = get_time() as f32;
-                    }
+                        // One ship, or two sharing the keyboard
+                        let previous = ui.button(vec2(20., 505.), "<");
+                        let next = ui.button(vec2(270., 505.), ">");
+                        if previous || next || (menu_focus == 6 && (menu.left || menu.right)) {
+                            chosen_players = chosen_players % player::MAX_PLAYERS + 1;
+                        }
+                        ui.label(
+                            vec2(110., 525.),
+                            if chosen_players > 1 { "2 Players" } else { "1 Player" },
+                        );
 
-                    if pc_last_dir_change > 0.0 && get_time() as f32 - pc_last_dir_change > 0.2 {
-                        ship_sprite.set_animation(2);
-                    }
-                    else {
-                        ship_sprite.set_animation(1);
-                    }
-                    
+                    },
+                );
+            }
+            GameState::Controls => {
+                if bindings_menu.update(&mut bindings, storage.as_ref(), gamepads.menu, &touch) {
+                    game_state = GameState::MainMenu;
                 }
-                if is_key_down(KeyCode::Down) {
-                    circle.y += circle.speed * delta_time;
+                render::draw_bindings(&bindings, &bindings_menu, screen_center.x, touch.active);
+            }
+            GameState::Settings => {
+                if settings_menu.update(&mut save_data, storage.as_ref(), gamepads.menu, &touch) {
+                    game_state = GameState::MainMenu;
                 }
-                if is_key_down(KeyCode::Up) {
-                    circle.y -= circle.speed * delta_time;
+                if canvas.integer_scaling() != save_data.integer_scaling {
+                    canvas.set_integer_scaling(save_data.integer_scaling);
                 }
-
-                // Pause Game
-                if is_key_pressed(KeyCode::Escape) {
+                render::draw_settings(&save_data, &settings_menu, screen_center.x, touch.active);
+            }
+            GameState::Playing => {
+                // Get delta time
+                let delta_time = get_frame_time();
+                let inputs = input::read_all(&bindings, &gamepads, &touch, save_data.mouse_control);
+                let input = inputs[0];
+
+                // Pause Game, or leave a replay
+                if inputs.iter().any(|input| input.pause) {
+                    if playback.take().is_some() {
+                        world.high_score = stored_high_score;
+                        game_state = GameState::MainMenu;
+                        canvas.present(&postfx.apply(canvas.texture(), |pass| {
+                            Setting::from(pass).enabled(&save_data)
+                        }));
+                        shader_watcher.draw_errors();
+                        next_frame().await;
+                        continue;
+                    }
                     game_state = GameState::Paused;
                 }
 
-                circle.x = clamp(circle.x, 0.0 + circle.size, screen_width() - circle.size);
-                circle.y = clamp(circle.y, 0.0 + circle.size, screen_height() - circle.size);
-
-                // Did player shoot? Has it been 0.25 seconds since the last shot?
-                if is_key_down(KeyCode::Space) && (get_time() - last_shot) > 0.25 {
-                    bullets.push(Shape {
-                        x: circle.x,
-                        y: circle.y - 24.0,
-                        speed: circle.speed * 2.0,
-                        size: 32.0,
-                        color: WHITE,
-                        collided: false,
-                    });
-                    play_sound_once(&resources.sound_laser);
-                    last_shot = get_time();
-                }
-
-                // --- Squares ---
-                // Create a new square
-                if rand::gen_range(0, 99) >= 95 {
-                    let size = rand::gen_range(16.0, 64.0);
-
-                    squares.push(Shape {
-                        size,
-                        speed: rand::gen_range(50.0, 150.0),
-                        x: rand::gen_range(size / 2.0, screen_width() - size / 2.0),
-                        y: -size,
-                        color: match enemy_clr.choose() {
-                            Some(choice) => *choice,
-                            None => Color::from_hex(0x000000),
-                        },
-                        collided: false,
-                    })
-                }
-
-                // Move the Squares
-                for square in &mut squares {
-                    square.y += square.speed * delta_time;
+                if input.right {
+                    direction_modifier += 0.05 * delta_time;
                 }
-
-                // Move the bullets
-                for bullet in &mut bullets {
-                    bullet.y -= bullet.speed * delta_time;
+                if input.left {
+                    direction_modifier -= 0.05 * delta_time;
                 }
 
-                ship_sprite.update();
-                bullet_sprite.update();
-                enemy_sm_sprite.update();
-                enemy_md_sprite.update();
-                enemy_lg_sprite.update();
-                
-                // Check for collision (Lose state)
-                if squares.iter().any(|square| circle.collides_with(square)) {
-                    if score == high_score {
-                        fs::write("highscore.dat", high_score.to_string()).ok();
+                let target = if save_data.mouse_control { pointer.update(&canvas, &input) } else { None };
+
+                let mut events = vec![];
+                let mut tick_inputs = inputs;
+                // Hit-stop holds the simulation while everything else carries on
+                let sim_time = if camera.frozen() { 0.0 } else { delta_time };
+                for _ in 0..timestep.advance(sim_time) {
+                    match &mut playback {
+                        Some(player) => match player.next_input() {
+                            Some(recorded) => tick_inputs = recorded,
+                            None => {
+                                // Ran out of input without the recorded game over
+                                events.push(WorldEvent::GameOver);
+                                break;
+                            }
+                        },
+                        None => {
+                            // Chase the cursor from wherever the last tick left the ship
+                            tick_inputs = inputs;
+                            match (target, world.players.first()) {
+                                (Some(target), Some(ship)) if ship.in_play() => {
+                                    tick_inputs[0].set_stick(pointer::steer_toward(
+                                        ship.position(),
+                                        target,
+                                        ship.ship.speed,
+                                        TICK,
+                                    ))
+                                }
+                                _ => {}
+                            }
+                            recording.record(tick_inputs);
+                        }
+                    }
+                    events.extend(world.step(TICK, &tick_inputs));
+                    if events.contains(&WorldEvent::GameOver) {
+                        break;
                     }
-                    game_state = GameState::GameOver;
                 }
-
-                // Check for bullet-square collisions
-                for square in squares.iter_mut() {
-                    for bullet in bullets.iter_mut() {
-                        if bullet.collides_with(square) {
-                            bullet.collided = true;
-                            square.collided = true;
-                            score += square.size.round() as u32;
-                            high_score = high_score.max(score);
+                for event in events {
+                    match event {
+                        WorldEvent::Shot => play_sound_once(&resources.sound_laser),
+                        WorldEvent::ChargedShot => play_sound(
+                            &resources.sound_laser,
+                            PlaySoundParams {
+                                looped: false,
+                                volume: 1.0,
+                            },
+                        ),
+                        WorldEvent::EnemyShot => play_sound(
+                            &resources.sound_laser,
+                            PlaySoundParams {
+                                looped: false,
+                                volume: 0.15,
+                            },
+                        ),
+                        WorldEvent::Hit { x, y } => {
                             explosions.push((
                                 Emitter::new(EmitterConfig {
-                                    amount: square.size.round() as u32 * 4,
+                                    texture: Some(resources.explosion_texture.clone()),
+                                    ..particle_debris()
+                                }),
+                                vec2(x, y),
+                            ));
+                        }
+                        WorldEvent::Explosion { x, y, size } => {
+                            explosions.push((
+                                Emitter::new(EmitterConfig {
+                                    amount: size.round() as u32 * 4,
                                     texture: Some(resources.explosion_texture.clone()),
                                     ..particle_explosion()
                                 }),
-                                vec2(square.x, square.y),
+                                vec2(x, y),
                             ));
                             play_sound_once(&resources.sound_explosion);
+                            if save_data.screen_shake {
+                                camera.add_trauma(EXPLOSION_TRAUMA * (size / BIG_KILL_SIZE).min(1.0));
+                            }
+                            if save_data.hit_stop && size >= BIG_KILL_SIZE {
+                                camera.hit_stop(BIG_KILL_STOP);
+                            }
+                        }
+                        WorldEvent::Stage(stage_event) => {
+                            let text = match stage_event {
+                                StageEvent::Started(index) => {
+                                    format!("Stage {}: {}", index + 1, world.data.levels[index].name)
+                                }
+                                StageEvent::Cleared(index) => format!("Stage {} clear!", index + 1),
+                                StageEvent::BossIncoming(kind) => {
+                                    format!("WARNING: {}", world.data.bosses[kind].name)
+                                }
+                                StageEvent::EndlessStarted => "Endless mode".to_string(),
+                            };
+                            banner = Some((text, get_time() + 2.5));
+                        }
+                        WorldEvent::BossPhase(phase) => {
+                            // The first phase starts right after the warning
+                            if phase > 0 {
+                                banner = Some((format!("Phase {}", phase + 1), get_time() + 1.5));
+                            }
+                        }
+                        WorldEvent::BossDefeated => {
+                            banner = Some(("Boss destroyed!".to_string(), get_time() + 2.5));
+                            if save_data.screen_shake {
+                                camera.add_trauma(BOSS_TRAUMA);
+                            }
+                            if save_data.hit_stop {
+                                camera.hit_stop(BOSS_KILL_STOP);
+                            }
+                        }
+                        WorldEvent::PowerUp(kind) => {
+                            banner = Some((format!("{}!", kind.label()), get_time() + 1.0));
+                        }
+                        WorldEvent::Bomb { x, y } => {
+                            explosions.push((
+                                Emitter::new(EmitterConfig {
+                                    amount: 400,
+                                    texture: Some(resources.explosion_texture.clone()),
+                                    ..particle_explosion()
+                                }),
+                                vec2(x, y),
+                            ));
+                            play_sound_once(&resources.sound_explosion);
+                            if save_data.screen_shake {
+                                camera.add_trauma(BOMB_TRAUMA);
+                            }
+                        }
+                        WorldEvent::PlayerDied { x, y } => {
+                            explosions.push((
+                                Emitter::new(EmitterConfig {
+                                    amount: 160,
+                                    texture: Some(resources.explosion_texture.clone()),
+                                    ..particle_explosion()
+                                }),
+                                vec2(x, y),
+                            ));
+                            play_sound_once(&resources.sound_explosion);
+                            if save_data.screen_shake {
+                                camera.add_trauma(DEATH_TRAUMA);
+                            }
+                        }
+                        WorldEvent::Respawned => {}
+                        WorldEvent::Revived(index) => {
+                            banner = Some((format!("P{} is back!", index + 1), get_time() + 1.5));
+                        }
+                        WorldEvent::Scored { x, y, points } => sprites.add_popup(x, y, points),
+                        WorldEvent::Continued => {
+                            banner = Some(("Continue!".to_string(), get_time() + 1.5));
+                        }
+                        WorldEvent::ShieldBroken { x, y } => {
+                            explosions.push((
+                                Emitter::new(EmitterConfig {
+                                    texture: Some(resources.explosion_texture.clone()),
+                                    ..particle_debris()
+                                }),
+                                vec2(x, y),
+                            ));
+                            play_sound_once(&resources.sound_explosion);
+                        }
+                        WorldEvent::GameOver => {
+                            if let Some(player) = playback.take() {
+                                if player.verify(world.tick, world.score) {
+                                    info!("Replay matched: score {} at tick {}", world.score, world.tick);
+                                } else {
+                                    warn!(
+                                        "Replay diverged: recorded score {} at tick {}, got {} at tick {}",
+                                        player.replay.final_score,
+                                        player.replay.final_tick,
+                                        world.score,
+                                        world.tick,
+                                    );
+                                }
+                                world.high_score = stored_high_score;
+                            } else {
+                                // Saved right away, so closing the game before
+                                // typing a name still keeps the score
+                                let rank = if save_data.qualifies(world.score) {
+                                    save_data.insert(ScoreEntry {
+                                        name: save_data.last_name.clone(),
+                                        score: world.score,
+                                        timestamp: miniquad::date::now() as u64,
+                                        seed: recording.seed,
+                                        duration: world.time as f32,
+                                        difficulty: world.preset().name.clone(),
+                                        players: world.players.len(),
+                                    })
+                                } else {
+                                    None
+                                };
+                                if let Some(rank) = rank {
+                                    world.high_score = save_data.high_score();
+                                    if let Err(err) = save_data.save(storage.as_ref()) {
+                                        error!("Could not save high scores: {}", err);
+                                    }
+                                    pending_entry = Some((rank, save_data.last_name.clone()));
+                                }
+                                recording.finish(world.tick, world.score);
+                                if let Err(err) = recording.save(storage.as_ref(), LAST_REPLAY_FILE) {
+                                    error!("Could not save replay: {}", err);
+                                }
+                            }
+                            game_state = GameState::GameOver;
                         }
                     }
                 }
 
-                // Keep squares that's on-screen
-                squares.retain(|square| square.y < screen_height() + square.size);
-
-                // Keep bullets that's on-screen
-                bullets.retain(|bullet| bullet.y > 0.0 - bullet.size / 2.0);
-
-                // Retain active entities
-                squares.retain(|square| !square.collided);
-                bullets.retain(|bullet| !bullet.collided);
+                sprites.update(&tick_inputs);
                 explosions.retain(|(explosion, _)| explosion.config.emitting);
-                
-                // Draw bullets
-                let bullet_frame = bullet_sprite.frame();
-                for bullet in &bullets {
-                    // draw_circle(bullet.x, bullet.y, bullet.size / 2.0, RED);
-                    draw_texture_ex(
-                        &resources.bullet_texture, 
-                        bullet.x - bullet.size / 2.0, 
-                        bullet.y - bullet.size / 2.0, 
-                        bullet.color, 
-                        DrawTextureParams {
-                            dest_size: Some(vec2(bullet.size, bullet.size)),
-                            source: Some(bullet_frame.source_rect),
-                            ..Default::default()
-                        },
-                    );
-                }
 
-                // Draw the player (ship)
-                // draw_circle(circle.x, circle.y, circle.size, circle.color);
-                let ship_frame = ship_sprite.frame();
-                draw_texture_ex(
-                    &resources.ship_texture, 
-                    circle.x - ship_frame.dest_size.x, 
-                    circle.y - ship_frame.dest_size.y, 
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(ship_frame.dest_size * 2.0),
-                        source: Some(ship_frame.source_rect),
-                        ..Default::default()
-                    },
-                );
+                // Zoom in on a boss flying in
+                let focus = world
+                    .boss
+                    .as_ref()
+                    .filter(|boss| save_data.boss_zoom && boss.state == BossState::Intro)
+                    .map(|boss| vec2(boss.shape.x, boss.shape.y));
+                camera.update(delta_time, focus);
 
-                // Draw the squares
-                for square in &squares {
-                    // Pick a texture based on enemy size
-                    let enemy_frame;
-                    let enemy_texture;
-                    match square.size {
-                        size if size > 48.0 => {
-                            enemy_frame = enemy_lg_sprite.frame();
-                            enemy_texture = &resources.enemy_big_texture;
-                        }
-                        size if size > 24.0 => {
-                            enemy_frame = enemy_md_sprite.frame();
-                            enemy_texture = &resources.enemy_med_texture;
-                        }
-                        _ => {
-                            enemy_frame = enemy_sm_sprite.frame();
-                            enemy_texture = &resources.enemy_small_texture;
-                        }
-                    }
-
-                    // Draw the enemy
-                    draw_texture_ex(
-                        enemy_texture, 
-                        square.x - square.size / 2.0, 
-                        square.y - square.size / 2.0, 
-                        WHITE, 
-                        DrawTextureParams {
-                            dest_size: Some(vec2(square.size, square.size)),
-                            source: Some(enemy_frame.source_rect),
-                            ..Default::default()
-                        },
-                    );
-                }
+                // The playfield moves with the camera, the HUD stays put
+                set_camera(&camera.view(canvas.camera()));
+                sprites.draw_world(&world, &resources, timestep.alpha());
 
                 // Draw explosions
                 for (explosion, coords) in explosions.iter_mut() {
                     explosion.draw(*coords);
                 }
+                canvas.begin();
 
                 // Draw scores
-                draw_text(
-                    format!("Score: {}", score).as_str(),
-                    10.0,
-                    35.0,
-                    25.0,
-                    WHITE,
-                );
-
-                let highscore_text = format!("High score: {}", high_score);
-                let text_dimensions = measure_text(highscore_text.as_str(), None, 25, 1.0);
-                draw_text(
-                    highscore_text.as_str(),
-                    screen_width() - text_dimensions.width - 10.0,
-                    35.0,
-                    25.0,
-                    YELLOW,
-                );
+                sprites.draw_scores(&world);
+                sprites.draw_boss_bar(&world, &resources.font);
+                sprites.draw_continue(&world, &bindings);
+                touch.draw();
+                if let Some((text, until)) = &banner {
+                    if get_time() < *until {
+                        let text_dimensions = measure_text(text, None, 40, 1.0);
+                        draw_text(
+                            text,
+                            screen_center.x - text_dimensions.width / 2.0,
+                            screen_center.y * 0.6,
+                            40.0,
+                            YELLOW,
+                        );
+                    }
+                }
+                if playback.is_some() {
+                    let text = format!("REPLAY - [Escape] or {} to exit", bindings.key_label(0, Action::Pause));
+                    let text_dimensions = measure_text(&text, None, 25, 1.0);
+                    draw_text(
+                        &text,
+                        screen_center.x - text_dimensions.width / 2.0,
+                        canvas::HEIGHT - 20.0,
+                        25.0,
+                        RED,
+                    );
+                }
             }
             GameState::Paused => {
                 stop_sound(&resources.theme_music);
-                if is_key_pressed(KeyCode::Space) {
+                if bindings.any_pressed(Action::Fire)
+                    || bindings.any_pressed(Action::Pause)
+                    || is_key_pressed(KeyCode::Escape)
+                    || gamepads.menu.confirm
+                    || touch.tapped
+                {
+                    disconnected = None;
                     // Play music
                     play_sound(
                         &resources.theme_music,
@@ -565,10 +771,25 @@ async fn main() -> Result<(), macroquad::Error> {
                     50.0,
                     WHITE,
                 );
-                let instruction_txt = "Resume with [Space]";
-                let instruct_txt_dim = measure_text(instruction_txt, None, 24, 1.0);
+                if let Some(player) = disconnected {
+                    let disconnected_txt = format!("Controller for P{} disconnected", player + 1);
+                    let disconnected_dim = measure_text(&disconnected_txt, None, 24, 1.0);
+                    draw_text(
+                        &disconnected_txt,
+                        screen_center.x - disconnected_dim.width / 2.0,
+                        screen_center.y + 40.0,
+                        24.0,
+                        ORANGE,
+                    );
+                }
+                let instruction_txt = if touch.active {
+                    "Tap to resume".to_string()
+                } else {
+                    format!("Resume with {} or Start", bindings.key_label(0, Action::Fire))
+                };
+                let instruct_txt_dim = measure_text(&instruction_txt, None, 24, 1.0);
                 draw_text(
-                    instruction_txt,
+                    &instruction_txt,
                     screen_center.x - instruct_txt_dim.width / 2.0,
                     25.0 + (instruct_txt_dim.offset_y / 2.0),
                     24.0,
@@ -577,7 +798,30 @@ async fn main() -> Result<(), macroquad::Error> {
             }
             GameState::GameOver => {
                 // Responding to player input
-                if is_key_pressed(KeyCode::Space) {
+                if let Some((rank, name)) = &mut pending_entry {
+                    while let Some(character) = get_char_pressed() {
+                        if (character.is_alphanumeric() || character == ' ' && !name.is_empty())
+                            && name.chars().count() < MAX_NAME_LEN
+                        {
+                            name.push(character);
+                        }
+                    }
+                    if is_key_pressed(KeyCode::Backspace) {
+                        name.pop();
+                    }
+                    // Phones and controllers have no keyboard to type a name with
+                    let typeless = touch.tapped || gamepads.menu.confirm;
+                    if typeless && name.trim().is_empty() {
+                        *name = touch::TOUCH_NAME.to_string();
+                    }
+                    if (is_key_pressed(KeyCode::Enter) || typeless) && !name.trim().is_empty() {
+                        save_data.rename(*rank, name.trim());
+                        pending_entry = None;
+                        if let Err(err) = save_data.save(storage.as_ref()) {
+                            error!("Could not save high scores: {}", err);
+                        }
+                    }
+                } else if bindings.any_pressed(Action::Fire) || gamepads.menu.confirm || touch.tapped {
                     game_state = GameState::MainMenu;
                 }
 
@@ -591,8 +835,8 @@ async fn main() -> Result<(), macroquad::Error> {
                     RED,
                 );
 
-                if score == high_score {
-                    let hiscore_congrats_txt = format!("Congrats on beating the high score with {}!", score);
+                if world.score == world.high_score {
+                    let hiscore_congrats_txt = format!("Congrats on beating the high score with {}!", world.score);
                     let hiscore_text_dim = measure_text(&hiscore_congrats_txt, None, 24, 1.0);
                     draw_text(
                         &hiscore_congrats_txt,
@@ -603,11 +847,43 @@ async fn main() -> Result<(), macroquad::Error> {
                     );
                 }
 
+                let chain_txt = format!("Best chain: {}", world.combo.best_chain);
+                let chain_txt_dim = measure_text(&chain_txt, None, 24, 1.0);
+                draw_text(
+                    &chain_txt,
+                    screen_center.x - chain_txt_dim.width / 2.0,
+                    screen_center.y - text_dimensions.height,
+                    24.0,
+                    ORANGE,
+                );
+
+                let board_top = screen_center.y + text_dimensions.offset_y / 2.0 + 80.0;
+                if let Some((_, name)) = &pending_entry {
+                    let name_txt = format!("Enter your name: {}_", name);
+                    let name_txt_dim = measure_text(&name_txt, None, 24, 1.0);
+                    draw_text(
+                        &name_txt,
+                        screen_center.x - name_txt_dim.width / 2.0,
+                        board_top,
+                        24.0,
+                        YELLOW,
+                    );
+                } else {
+                    render::draw_leaderboard(&save_data, screen_center.x, board_top);
+                }
+
                 // Instructions
-                let instruction_txt = "Return to main menu with [Space]";
-                let instruct_txt_dim = measure_text(instruction_txt, None, 24, 1.0);
+                let instruction_txt = match (touch.active, pending_entry.is_some()) {
+                    (true, true) => "Tap to confirm your name".to_string(),
+                    (true, false) => "Tap to return to main menu".to_string(),
+                    (false, true) => "Confirm your name with [Enter] or Start".to_string(),
+                    (false, false) => {
+                        format!("Return to main menu with {} or Start", bindings.key_label(0, Action::Fire))
+                    }
+                };
+                let instruct_txt_dim = measure_text(&instruction_txt, None, 24, 1.0);
                 draw_text(
-                    instruction_txt,
+                    &instruction_txt,
                     screen_center.x - instruct_txt_dim.width / 2.0,
                     25.0 + (instruct_txt_dim.offset_y / 2.0),
                     24.0,
@@ -616,7 +892,23 @@ async fn main() -> Result<(), macroquad::Error> {
             }
         }
 
+        if cfg!(debug_assertions) {
+            if is_key_pressed(KeyCode::F3) {
+                show_shader_panel = !show_shader_panel;
+            }
+            if show_shader_panel {
+                root_ui().window(hash!(), vec2(10., 10.), vec2(460., 190.), |ui| {
+                    ui.label(None, "Starfield uniforms [F3]");
+                    ui.slider(hash!(), "direction_modifier", -1.0..1.0, &mut direction_modifier);
+                    ui.slider(hash!(), "num_layers", 1.0..16.0, &mut num_layers);
+                });
+                num_layers = num_layers.round();
+            }
+        }
 
+        canvas.present(&postfx.apply(canvas.texture(), |pass| Setting::from(pass).enabled(&save_data)));
+        // Straight onto the window, so a broken pass can't garble them
+        shader_watcher.draw_errors();
         next_frame().await
     }
 }
\ No newline at end of file
@@ -0,0 +1,343 @@
+use crate::projectile::{Projectile, ProjectileKind};
+use macroquad::prelude::*;
+use serde::Deserialize;
+use std::rc::Rc;
+
+pub const PATTERNS_DIR: &str = "patterns";
+// Lists the pattern files to load, since the web build can't read directories
+pub const PATTERN_INDEX: &str = "patterns/index.ron";
+
+// One instruction of a pattern file. Angles are in degrees, with 0 pointing
+// straight down; speeds are in pixels per second.
+#[derive(Clone, Debug, Deserialize)]
+pub enum Step {
+    // `count` shots evenly around a circle, rotated by `offset` or aimed at
+    // the player
+    Ring {
+        count: u32,
+        speed: f32,
+        #[serde(default)]
+        offset: f32,
+        #[serde(default)]
+        aimed: bool,
+    },
+    // `count` shots spread over `angle`, centered on the player
+    Fan { count: u32, angle: f32, speed: f32 },
+    // `shots` rings of `arms` shots, `interval` seconds apart, each turned
+    // `turn` degrees further than the last
+    Spiral {
+        arms: u32,
+        shots: u32,
+        interval: f32,
+        turn: f32,
+        speed: f32,
+    },
+    // A ring placed `radius` around the shooter that hangs in place for
+    // `delay` seconds before flying outwards
+    Burst {
+        count: u32,
+        radius: f32,
+        delay: f32,
+        speed: f32,
+    },
+    Wait(f32),
+    Repeat { times: u32, steps: Vec<Step> },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PatternDef {
+    pub name: String,
+    #[serde(default = "default_projectile")]
+    pub projectile: ProjectileKind,
+    #[serde(default = "default_color")]
+    pub color: u32,
+    pub steps: Vec<Step>,
+}
+
+fn default_projectile() -> ProjectileKind {
+    ProjectileKind::Bullet
+}
+
+fn default_color() -> u32 {
+    0xff7a6e
+}
+
+// A single volley, fired at a fixed time into the pattern
+#[derive(Clone, Debug)]
+enum Emit {
+    Ring { count: u32, speed: f32, offset: f32, aimed: bool },
+    Fan { count: u32, angle: f32, speed: f32 },
+    Burst { count: u32, radius: f32, delay: f32, speed: f32 },
+}
+
+// A pattern flattened into a timeline of volleys
+#[derive(Debug)]
+pub struct Pattern {
+    pub name: String,
+    pub projectile: ProjectileKind,
+    pub color: Color,
+    schedule: Vec<(f32, Emit)>,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Pattern, ron::error::SpannedError> {
+        let def: PatternDef = ron::from_str(text)?;
+        let mut schedule = vec![];
+        flatten(&def.steps, 0.0, &mut schedule);
+        Ok(Pattern {
+            name: def.name,
+            projectile: def.projectile,
+            color: Color::from_hex(def.color),
+            schedule,
+        })
+    }
+}
+
+// Append the volleys of `steps` starting at `time`, returning the end time
+fn flatten(steps: &[Step], mut time: f32, schedule: &mut Vec<(f32, Emit)>) -> f32 {
+    for step in steps {
+        match step {
+            Step::Ring {
+                count,
+                speed,
+                offset,
+                aimed,
+            } => schedule.push((
+                time,
+                Emit::Ring {
+                    count: *count,
+                    speed: *speed,
+                    offset: *offset,
+                    aimed: *aimed,
+                },
+            )),
+            Step::Fan { count, angle, speed } => schedule.push((
+                time,
+                Emit::Fan {
+                    count: *count,
+                    angle: *angle,
+                    speed: *speed,
+                },
+            )),
+            Step::Spiral {
+                arms,
+                shots,
+                interval,
+                turn,
+                speed,
+            } => {
+                for shot in 0..*shots {
+                    schedule.push((
+                        time,
+                        Emit::Ring {
+                            count: *arms,
+                            speed: *speed,
+                            offset: turn * shot as f32,
+                            aimed: false,
+                        },
+                    ));
+                    time += interval;
+                }
+            }
+            Step::Burst {
+                count,
+                radius,
+                delay,
+                speed,
+            } => schedule.push((
+                time,
+                Emit::Burst {
+                    count: *count,
+                    radius: *radius,
+                    delay: *delay,
+                    speed: *speed,
+                },
+            )),
+            Step::Wait(seconds) => time += seconds,
+            Step::Repeat { times, steps } => {
+                for _ in 0..*times {
+                    time = flatten(steps, time, schedule);
+                }
+            }
+        }
+    }
+    time
+}
+
+// Plays a pattern from a moving origin
+pub struct PatternRunner {
+    pub pattern: Rc<Pattern>,
+    time: f32,
+    next: usize,
+}
+
+impl PatternRunner {
+    pub fn new(pattern: Rc<Pattern>) -> Self {
+        PatternRunner {
+            pattern,
+            time: 0.0,
+            next: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.pattern.schedule.len()
+    }
+
+    // Advance by `dt`, pushing every volley that came due into `out`.
+    // Returns how many volleys were fired.
+    pub fn update(&mut self, dt: f32, origin: Vec2, target: Vec2, out: &mut Vec<Projectile>) -> usize {
+        self.time += dt;
+        let mut fired = 0;
+        while let Some((at, emit)) = self.pattern.schedule.get(self.next) {
+            if *at > self.time {
+                break;
+            }
+            self.fire(emit, origin, target, out);
+            self.next += 1;
+            fired += 1;
+        }
+        fired
+    }
+
+    fn fire(&self, emit: &Emit, origin: Vec2, target: Vec2, out: &mut Vec<Projectile>) {
+        let down = vec2(0.0, 1.0);
+        let aim = (target - origin).try_normalize().unwrap_or(down);
+        let mut shoot = |position: Vec2, direction: Vec2, speed: f32, delay: f32| {
+            let mut projectile = Projectile::new(
+                position,
+                direction * speed,
+                self.pattern.projectile,
+                self.pattern.color,
+            );
+            projectile.delay = delay;
+            out.push(projectile);
+        };
+
+        match emit {
+            Emit::Ring {
+                count,
+                speed,
+                offset,
+                aimed,
+            } => {
+                let base = if *aimed { aim } else { down };
+                for i in 0..*count {
+                    let angle = offset.to_radians() + std::f32::consts::TAU * i as f32 / *count as f32;
+                    shoot(origin, Vec2::from_angle(angle).rotate(base), *speed, 0.0);
+                }
+            }
+            Emit::Fan { count, angle, speed } => {
+                let step = if *count > 1 {
+                    angle.to_radians() / (*count - 1) as f32
+                } else {
+                    0.0
+                };
+                let start = -step * (*count as f32 - 1.0) / 2.0;
+                for i in 0..*count {
+                    shoot(origin, Vec2::from_angle(start + step * i as f32).rotate(aim), *speed, 0.0);
+                }
+            }
+            Emit::Burst {
+                count,
+                radius,
+                delay,
+                speed,
+            } => {
+                for i in 0..*count {
+                    let direction = Vec2::from_angle(std::f32::consts::TAU * i as f32 / *count as f32).rotate(down);
+                    shoot(origin + direction * *radius, direction, *speed, *delay);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runner(steps: &str) -> PatternRunner {
+        let text = format!("(name: \"test\", steps: {})", steps);
+        PatternRunner::new(Rc::new(Pattern::parse(&text).unwrap()))
+    }
+
+    // Advance `seconds` at 120 Hz, counting the volleys fired and keeping the bullets
+    fn run(runner: &mut PatternRunner, seconds: f32, out: &mut Vec<Projectile>) -> usize {
+        let dt = 1.0 / 120.0;
+        (0..(seconds / dt).round() as usize)
+            .map(|_| runner.update(dt, Vec2::ZERO, vec2(0.0, 100.0), out))
+            .sum()
+    }
+
+    #[test]
+    fn ring_fires_all_at_once() {
+        let mut runner = runner("[Ring(count: 12, speed: 100.0)]");
+        let mut out = vec![];
+        assert_eq!(runner.update(0.001, Vec2::ZERO, vec2(0.0, 100.0), &mut out), 1);
+        assert_eq!(out.len(), 12);
+        assert!(out.iter().all(|bullet| (bullet.velocity.length() - 100.0).abs() < 0.01));
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn aimed_fan_is_centered_on_the_target() {
+        let mut runner = runner("[Fan(count: 3, angle: 30.0, speed: 50.0)]");
+        let mut out = vec![];
+        runner.update(0.001, Vec2::ZERO, vec2(100.0, 0.0), &mut out);
+        assert_eq!(out.len(), 3);
+        let middle = out[1].velocity.normalize();
+        assert!(middle.distance(vec2(1.0, 0.0)) < 0.001);
+        let edge = out[0].velocity.angle_between(out[2].velocity).abs().to_degrees();
+        assert!((edge - 30.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn spiral_fires_on_its_interval() {
+        let mut runner = runner("[Spiral(arms: 3, shots: 4, interval: 0.25, turn: 10.0, speed: 80.0)]");
+        let mut out = vec![];
+        assert_eq!(run(&mut runner, 0.1, &mut out), 1);
+        assert_eq!(out.len(), 3);
+        assert_eq!(run(&mut runner, 0.5, &mut out), 2);
+        assert!(!runner.is_finished());
+        assert_eq!(run(&mut runner, 0.2, &mut out), 1);
+        assert_eq!(out.len(), 12);
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn waits_and_repeats_add_up() {
+        let mut runner = runner(
+            "[Wait(0.5), Repeat(times: 3, steps: [Ring(count: 2, speed: 10.0), Wait(1.0)]), Fan(count: 1, angle: 0.0, speed: 10.0)]",
+        );
+        let mut out = vec![];
+        assert_eq!(run(&mut runner, 0.45, &mut out), 0);
+        assert_eq!(run(&mut runner, 0.1, &mut out), 1);
+        assert_eq!(run(&mut runner, 2.0, &mut out), 2);
+        // The fan follows the last ring's wait, at 3.5 seconds
+        assert_eq!(run(&mut runner, 0.9, &mut out), 0);
+        assert_eq!(run(&mut runner, 0.1, &mut out), 1);
+        assert_eq!(out.len(), 7);
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn burst_hangs_around_the_origin() {
+        let mut runner = runner("[Burst(count: 4, radius: 20.0, delay: 0.5, speed: 60.0)]");
+        let mut out = vec![];
+        runner.update(0.001, Vec2::ZERO, vec2(0.0, 100.0), &mut out);
+        assert_eq!(out.len(), 4);
+        for bullet in &out {
+            assert!((vec2(bullet.shape.x, bullet.shape.y).length() - 20.0).abs() < 0.01);
+            assert_eq!(bullet.delay, 0.5);
+        }
+    }
+
+    #[test]
+    fn long_frames_catch_up() {
+        let mut runner = runner("[Spiral(arms: 1, shots: 5, interval: 0.1, turn: 0.0, speed: 10.0)]");
+        let mut out = vec![];
+        assert_eq!(runner.update(1.0, Vec2::ZERO, Vec2::ZERO, &mut out), 5);
+        assert!(runner.is_finished());
+    }
+}
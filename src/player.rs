@@ -0,0 +1,179 @@
+use crate::data::GameData;
+use crate::difficulty::Difficulty;
+use crate::input::Input;
+use crate::powerup::{Effects, PowerUpKind};
+use crate::shape::Shape;
+use crate::weapon::Weapon;
+use macroquad::prelude::*;
+
+// Ships that can share the screen in co-op
+pub const MAX_PLAYERS: usize = 2;
+// "Player entity"
+pub const SPEED: f32 = 200.0;
+// Side of the square around the ship's center that enemy shots can hit
+const HITBOX: f32 = 12.0;
+
+const SHIP_COLORS: [u32; MAX_PLAYERS] = [0xb1de78, 0x78c8de];
+
+// One ship and everything that belongs to whoever flies it
+pub struct Player {
+    pub ship: Shape,
+    pub weapon: Weapon,
+    pub effects: Effects,
+    // Seconds left before the ship can be hurt again
+    pub invulnerable: f32,
+    // Ships left, including the one in play
+    pub lives: u32,
+    // Seconds until the next ship flies in, 0 while one is in play
+    pub respawn: f32,
+    // Out of ships, with the wreck waiting for a partner to revive it
+    pub down: bool,
+    // Seconds a partner has spent next to the wreck
+    pub revive: f32,
+    // This player's part of the team score
+    pub score: u32,
+    // Seconds without a shield, for shields that regenerate
+    shield_time: f32,
+    // Whether the bomb key was down last step, so holding it drops just one
+    bomb_held: bool,
+}
+
+impl Player {
+    // Player `index` of `count`, spread out across the middle of the screen
+    pub fn new(index: usize, count: usize, data: &GameData, preset: &Difficulty, width: f32, height: f32) -> Self {
+        let x = spawn_x(index, count, width);
+        let y = height / 2.0;
+        Player {
+            ship: Shape {
+                size: 32.0,
+                speed: SPEED,
+                x,
+                y,
+                prev_x: x,
+                prev_y: y,
+                color: Color::from_hex(SHIP_COLORS[index % MAX_PLAYERS]),
+                collided: false,
+            },
+            weapon: Weapon::new(&data.weapon),
+            effects: Effects::default(),
+            invulnerable: 0.0,
+            lives: preset.lives,
+            respawn: 0.0,
+            down: false,
+            revive: 0.0,
+            score: 0,
+            shield_time: 0.0,
+            bomb_held: false,
+        }
+    }
+
+    pub fn in_play(&self) -> bool {
+        self.respawn <= 0.0 && !self.down
+    }
+
+    pub fn position(&self) -> Vec2 {
+        vec2(self.ship.x, self.ship.y)
+    }
+
+    // The small square enemy shots have to hit
+    pub fn core(&self) -> Rect {
+        Rect::new(
+            self.ship.x - HITBOX / 2.0,
+            self.ship.y - HITBOX / 2.0,
+            HITBOX,
+            HITBOX,
+        )
+    }
+
+    // Run down the timers, regenerating the shield if the preset allows it
+    pub fn update(&mut self, dt: f32, preset: &Difficulty) {
+        self.effects.update(dt);
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+        if let Some(regen) = preset.shield_regen {
+            if self.effects.shield > 0.0 {
+                self.shield_time = 0.0;
+            } else {
+                self.shield_time += dt;
+                if self.shield_time >= regen {
+                    self.effects.apply(PowerUpKind::Shield);
+                }
+            }
+        }
+    }
+
+    pub fn steer(&mut self, input: &Input, dt: f32, width: f32, height: f32) {
+        let ship = &mut self.ship;
+        // A tilted stick steers with its tilt, otherwise the keys at full speed
+        let stick = input.stick();
+        if stick != Vec2::ZERO {
+            ship.x += stick.x * ship.speed * dt;
+            ship.y += stick.y * ship.speed * dt;
+        } else {
+            if input.right {
+                ship.x += ship.speed * dt;
+            }
+            if input.left {
+                ship.x -= ship.speed * dt;
+            }
+            if input.down {
+                ship.y += ship.speed * dt;
+            }
+            if input.up {
+                ship.y -= ship.speed * dt;
+            }
+        }
+
+        ship.x = clamp(ship.x, 0.0 + ship.size, width - ship.size);
+        ship.y = clamp(ship.y, 0.0 + ship.size, height - ship.size);
+    }
+
+    // True once per press of the bomb key while there are bombs left
+    pub fn take_bomb(&mut self, pressed: bool) -> bool {
+        let fresh = pressed && !self.bomb_held;
+        self.bomb_held = pressed;
+        if fresh && self.weapon.bombs > 0 {
+            self.weapon.bombs -= 1;
+            return true;
+        }
+        false
+    }
+
+    // Fly the next ship in from the bottom of the player's column
+    pub fn respawn_at(&mut self, x: f32, y: f32, invulnerability: f32) {
+        self.respawn = 0.0;
+        self.ship.x = x;
+        self.ship.y = y;
+        self.ship.store_position();
+        self.invulnerable = invulnerability;
+    }
+
+    // Back in the fight where the wreck was, with a single ship
+    pub fn revived(&mut self, invulnerability: f32) {
+        self.down = false;
+        self.revive = 0.0;
+        self.lives = 1;
+        self.invulnerable = invulnerability;
+    }
+
+    // The ship was destroyed; the next one starts without power-ups and with
+    // the gun a tier down
+    pub fn lose_ship(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        self.effects = Effects::default();
+        self.shield_time = 0.0;
+        self.weapon.tier = self.weapon.tier.saturating_sub(1);
+    }
+}
+
+// Each player gets an equal slice of the screen's width
+pub fn spawn_x(index: usize, count: usize, width: f32) -> f32 {
+    width * (index + 1) as f32 / (count + 1) as f32
+}
+
+// The ship in `ships` closest to `from`
+pub fn nearest(ships: &[Vec2], from: Vec2) -> Option<Vec2> {
+    ships
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance_squared(from).total_cmp(&b.distance_squared(from)))
+}
@@ -0,0 +1,33 @@
+use crate::canvas::Canvas;
+use crate::input::Input;
+use macroquad::prelude::*;
+
+// The first ship chasing the mouse cursor, for players who picked mouse
+// control. The keys still work; pressing one hands steering back to them
+// until the mouse moves again.
+#[derive(Default)]
+pub struct Pointer {
+    following: bool,
+    last_position: Vec2,
+}
+
+impl Pointer {
+    // Where the ship should head this frame, if anywhere
+    pub fn update(&mut self, canvas: &Canvas, input: &Input) -> Option<Vec2> {
+        let position: Vec2 = mouse_position().into();
+        if position != self.last_position {
+            self.following = true;
+            self.last_position = position;
+        }
+        if input.left || input.right || input.up || input.down || input.stick != [0, 0] {
+            self.following = false;
+        }
+        self.following.then(|| canvas.to_logical(position))
+    }
+}
+
+// Stick tilt that carries a ship going at most `speed` from `from` onto
+// `target` within `dt`, so the recorded input replays exactly
+pub fn steer_toward(from: Vec2, target: Vec2, speed: f32, dt: f32) -> Vec2 {
+    ((target - from) / (speed * dt)).clamp_length_max(1.0)
+}
@@ -0,0 +1,129 @@
+use crate::canvas;
+use macroquad::prelude::*;
+
+const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+
+varying lowp vec2 uv;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    uv = texcoord;
+}
+";
+
+// Effects run over the finished frame, in the order they're applied
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pass {
+    Bloom,
+    ChromaticAberration,
+    Scanlines,
+    Crt,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [
+        Pass::Bloom,
+        Pass::ChromaticAberration,
+        Pass::Scanlines,
+        Pass::Crt,
+    ];
+
+    // Source file, under src/
+    pub fn file(self) -> &'static str {
+        match self {
+            Pass::Bloom => "bloom-shader.glsl",
+            Pass::ChromaticAberration => "chromatic-shader.glsl",
+            Pass::Scanlines => "scanlines-shader.glsl",
+            Pass::Crt => "crt-shader.glsl",
+        }
+    }
+
+    fn fragment_shader(self) -> &'static str {
+        match self {
+            Pass::Bloom => include_str!("bloom-shader.glsl"),
+            Pass::ChromaticAberration => include_str!("chromatic-shader.glsl"),
+            Pass::Scanlines => include_str!("scanlines-shader.glsl"),
+            Pass::Crt => include_str!("crt-shader.glsl"),
+        }
+    }
+}
+
+// Every pass' shader, and two canvas-sized targets to bounce the frame
+// between
+pub struct PostProcess {
+    materials: Vec<(Pass, Material)>,
+    targets: [(RenderTarget, Camera2D); 2],
+}
+
+impl PostProcess {
+    pub fn new() -> Result<Self, macroquad::Error> {
+        let mut materials = vec![];
+        for pass in Pass::ALL {
+            materials.push((pass, pass_material(pass.fragment_shader())?));
+        }
+        Ok(PostProcess {
+            materials,
+            targets: [target(), target()],
+        })
+    }
+
+    // Swap in a pass' shader edited on disk, if `file` belongs to one
+    pub fn reload(&mut self, file: &str, fragment: &str) -> Result<(), macroquad::Error> {
+        if let Some((_, material)) = self.materials.iter_mut().find(|(pass, _)| pass.file() == file) {
+            *material = pass_material(fragment)?;
+        }
+        Ok(())
+    }
+
+    // Run the enabled passes over `frame`, returning the finished picture
+    pub fn apply(&self, frame: &Texture2D, enabled: impl Fn(Pass) -> bool) -> Texture2D {
+        let mut source = frame.clone();
+        let passes = self.materials.iter().filter(|(pass, _)| enabled(*pass));
+        for ((_, material), (target, camera)) in passes.zip(self.targets.iter().cycle()) {
+            set_camera(camera);
+            material.set_uniform("iResolution", (canvas::WIDTH, canvas::HEIGHT));
+            gl_use_material(material);
+            draw_texture_ex(
+                &source,
+                0.0,
+                0.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(canvas::WIDTH, canvas::HEIGHT)),
+                    // Keep every pass the same way up as the canvas
+                    flip_y: true,
+                    ..Default::default()
+                },
+            );
+            gl_use_default_material();
+            source = target.texture.clone();
+        }
+        source
+    }
+}
+
+fn pass_material(fragment: &str) -> Result<Material, macroquad::Error> {
+    load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX_SHADER,
+            fragment,
+        },
+        MaterialParams {
+            uniforms: vec![UniformDesc::new("iResolution", UniformType::Float2)],
+            ..Default::default()
+        },
+    )
+}
+
+fn target() -> (RenderTarget, Camera2D) {
+    let target = render_target(canvas::WIDTH as u32, canvas::HEIGHT as u32);
+    target.texture.set_filter(FilterMode::Linear);
+    let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, canvas::WIDTH, canvas::HEIGHT));
+    camera.render_target = Some(target.clone());
+    (target, camera)
+}
@@ -0,0 +1,165 @@
+use crate::rng::Rng;
+use crate::shape::Shape;
+use macroquad::prelude::*;
+
+// How fast dropped power-ups fall, in pixels per second
+const FALL_SPEED: f32 = 80.0;
+const SIZE: f32 = 22.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PowerUpKind {
+    // Halves the time between shots
+    RapidFire,
+    // Adds a bullet on either side of the gun's fan
+    Spread,
+    // Absorbs one hit
+    Shield,
+    // Doubles the score of everything destroyed
+    Multiplier,
+    // One more bomb for the secondary fire key
+    Bomb,
+    // Raises the gun by one tier
+    Upgrade,
+}
+
+// (kind, drop weight)
+const DROPS: [(PowerUpKind, f32); 6] = [
+    (PowerUpKind::RapidFire, 3.0),
+    (PowerUpKind::Spread, 3.0),
+    (PowerUpKind::Shield, 2.0),
+    (PowerUpKind::Multiplier, 2.0),
+    (PowerUpKind::Bomb, 1.0),
+    (PowerUpKind::Upgrade, 2.0),
+];
+
+impl PowerUpKind {
+    // Pick a kind at random, weighted by `DROPS`
+    pub fn choose(rng: &mut Rng) -> PowerUpKind {
+        let total: f32 = DROPS.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.gen_range(0.0, total);
+        for (kind, weight) in DROPS {
+            if roll < weight {
+                return kind;
+            }
+            roll -= weight;
+        }
+        PowerUpKind::Bomb
+    }
+
+    // Seconds the effect lasts once picked up, 0 for instant ones
+    pub fn duration(self) -> f32 {
+        match self {
+            PowerUpKind::RapidFire => 8.0,
+            PowerUpKind::Spread => 8.0,
+            PowerUpKind::Shield => 12.0,
+            PowerUpKind::Multiplier => 10.0,
+            PowerUpKind::Bomb | PowerUpKind::Upgrade => 0.0,
+        }
+    }
+
+    // Short name for the pickup and the HUD
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUpKind::RapidFire => "RAPID",
+            PowerUpKind::Spread => "SPREAD",
+            PowerUpKind::Shield => "SHIELD",
+            PowerUpKind::Multiplier => "x2",
+            PowerUpKind::Bomb => "BOMB",
+            PowerUpKind::Upgrade => "POWER",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        Color::from_hex(match self {
+            PowerUpKind::RapidFire => 0xffd36e,
+            PowerUpKind::Spread => 0x6ec8ff,
+            PowerUpKind::Shield => 0x8cff9e,
+            PowerUpKind::Multiplier => 0xc48cff,
+            PowerUpKind::Bomb => 0xff7a6e,
+            PowerUpKind::Upgrade => 0xffffff,
+        })
+    }
+}
+
+// A power-up falling down the screen, waiting to be collected
+pub struct PowerUp {
+    pub shape: Shape,
+    pub kind: PowerUpKind,
+}
+
+impl PowerUp {
+    pub fn new(x: f32, y: f32, kind: PowerUpKind) -> Self {
+        PowerUp {
+            shape: Shape {
+                size: SIZE,
+                speed: FALL_SPEED,
+                x,
+                y,
+                prev_x: x,
+                prev_y: y,
+                color: kind.color(),
+                collided: false,
+            },
+            kind,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.shape.y += self.shape.speed * dt;
+    }
+}
+
+// Timed effects currently running on the ship, in seconds left
+#[derive(Default)]
+pub struct Effects {
+    pub rapid_fire: f32,
+    pub spread: f32,
+    pub shield: f32,
+    pub multiplier: f32,
+}
+
+impl Effects {
+    pub fn update(&mut self, dt: f32) {
+        for timer in [
+            &mut self.rapid_fire,
+            &mut self.spread,
+            &mut self.shield,
+            &mut self.multiplier,
+        ] {
+            *timer = (*timer - dt).max(0.0);
+        }
+    }
+
+    // Start or refresh the effect of a timed power-up
+    pub fn apply(&mut self, kind: PowerUpKind) {
+        let timer = match kind {
+            PowerUpKind::RapidFire => &mut self.rapid_fire,
+            PowerUpKind::Spread => &mut self.spread,
+            PowerUpKind::Shield => &mut self.shield,
+            PowerUpKind::Multiplier => &mut self.multiplier,
+            PowerUpKind::Bomb | PowerUpKind::Upgrade => return,
+        };
+        *timer = kind.duration();
+    }
+
+    // Running effects with their time left, for the HUD
+    pub fn active(&self) -> Vec<(PowerUpKind, f32)> {
+        [
+            (PowerUpKind::RapidFire, self.rapid_fire),
+            (PowerUpKind::Spread, self.spread),
+            (PowerUpKind::Shield, self.shield),
+            (PowerUpKind::Multiplier, self.multiplier),
+        ]
+        .into_iter()
+        .filter(|(_, time)| *time > 0.0)
+        .collect()
+    }
+
+    pub fn score_multiplier(&self) -> u32 {
+        if self.multiplier > 0.0 {
+            2
+        } else {
+            1
+        }
+    }
+}
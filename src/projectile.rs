@@ -0,0 +1,70 @@
+use crate::shape::Shape;
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+// Which row of laser-bolts.png a projectile is drawn with
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum ProjectileKind {
+    Bullet,
+    Bolt,
+}
+
+// A shot travelling in a straight line, fired by the player or enemies
+pub struct Projectile {
+    pub shape: Shape,
+    pub velocity: Vec2,
+    pub kind: ProjectileKind,
+    // Seconds the shot hangs in place before it starts moving
+    pub delay: f32,
+    pub damage: u32,
+    // Already counted as a near miss
+    pub grazed: bool,
+    // Index of the player who fired it, credited with what it destroys
+    pub owner: usize,
+}
+
+impl Projectile {
+    pub fn new(position: Vec2, velocity: Vec2, kind: ProjectileKind, color: Color) -> Self {
+        Projectile {
+            shape: Shape {
+                size: 16.0,
+                speed: velocity.length(),
+                x: position.x,
+                y: position.y,
+                prev_x: position.x,
+                prev_y: position.y,
+                color,
+                collided: false,
+            },
+            velocity,
+            kind,
+            delay: 0.0,
+            damage: 1,
+            grazed: false,
+            owner: 0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.delay > 0.0 {
+            self.delay -= dt;
+            return;
+        }
+        self.shape.x += self.velocity.x * dt;
+        self.shape.y += self.velocity.y * dt;
+    }
+
+    pub fn is_on_screen(&self, width: f32, height: f32) -> bool {
+        let margin = self.shape.size;
+        self.shape.x > -margin
+            && self.shape.x < width + margin
+            && self.shape.y > -margin
+            && self.shape.y < height + margin
+    }
+
+    // Smaller than the sprite so grazing shots don't feel unfair
+    pub fn hitbox(&self) -> Rect {
+        let size = self.shape.size / 2.0;
+        Rect::new(self.shape.x - size / 2.0, self.shape.y - size / 2.0, size, size)
+    }
+}
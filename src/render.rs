@@ -0,0 +1,757 @@
+use crate::assets::Resources;
+use crate::bindings::{Action, Bindings, BindingsMenu};
+use crate::boss::BossState;
+use crate::canvas;
+use crate::data::GameData;
+use crate::input::Input;
+use crate::player::MAX_PLAYERS;
+use crate::powerup::PowerUpKind;
+use crate::projectile::ProjectileKind;
+use crate::save::{self, SaveData};
+use crate::settings::{self, Setting, SettingsMenu};
+use crate::combo::CHAIN_WINDOW;
+use crate::touch;
+use crate::world::{World, CONTINUE_TIME, REVIVE_TIME};
+use macroquad::{
+    experimental::animation::{AnimatedSprite, Animation},
+    prelude::*,
+};
+
+const FLASH_VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+";
+
+// Draws the sprite's silhouette in solid white
+const FLASH_FRAGMENT_SHADER: &str = "#version 100
+precision lowp float;
+varying vec2 uv;
+varying vec4 color;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = vec4(1.0, 1.0, 1.0, texture2D(Texture, uv).a * color.a);
+}
+";
+
+// Seconds a score popup floats before fading out
+const POPUP_TIME: f32 = 0.8;
+// Sideways stick tilt, out of 127, at which the ship leans
+const STICK_LEAN: i8 = 32;
+// Tint of each player's ship sprite
+const SHIP_TINTS: [u32; MAX_PLAYERS] = [0xffffff, 0x9ad8ff];
+
+// Points floating up from where they were scored
+struct Popup {
+    text: String,
+    position: Vec2,
+    age: f32,
+}
+
+// Animation state for everything drawn from the world
+pub struct Sprites {
+    pub bullet: AnimatedSprite,
+    pub bolt: AnimatedSprite,
+    // One per player
+    pub ships: Vec<AnimatedSprite>,
+    // One per enemy archetype, in the same order
+    pub enemies: Vec<AnimatedSprite>,
+    // One per part of each boss
+    pub bosses: Vec<Vec<AnimatedSprite>>,
+    flash_material: Material,
+    popups: Vec<Popup>,
+    pc_last_dir_change: [f32; MAX_PLAYERS],
+}
+
+impl Sprites {
+    pub fn new(data: &GameData) -> Result<Self, macroquad::Error> {
+        // Setup bullet sprites, one per row of the sheet
+        let bullet = laser_sprite(0);
+        let bolt = laser_sprite(1);
+
+        // Setup ship sprites, leaning separately for each player
+        let ships = (0..MAX_PLAYERS).map(|_| ship_sprite()).collect();
+
+        // Setup enemy sprites
+        let enemies = data
+            .enemies
+            .iter()
+            .map(|archetype| {
+                AnimatedSprite::new(
+                    archetype.frame_size.0,
+                    archetype.frame_size.1,
+                    &[Animation {
+                        name: archetype.name.clone(),
+                        row: 0,
+                        frames: archetype.frames,
+                        fps: archetype.fps,
+                    }],
+                    true,
+                )
+            })
+            .collect();
+
+        // Setup boss part sprites
+        let bosses = data
+            .bosses
+            .iter()
+            .map(|boss| {
+                boss.parts
+                    .iter()
+                    .map(|part| {
+                        AnimatedSprite::new(
+                            part.frame_size.0,
+                            part.frame_size.1,
+                            &[Animation {
+                                name: part.name.clone(),
+                                row: 0,
+                                frames: part.frames,
+                                fps: part.fps,
+                            }],
+                            true,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let flash_material = load_material(
+            ShaderSource::Glsl {
+                vertex: FLASH_VERTEX_SHADER,
+                fragment: FLASH_FRAGMENT_SHADER,
+            },
+            MaterialParams::default(),
+        )?;
+
+        Ok(Sprites {
+            bullet,
+            bolt,
+            ships,
+            enemies,
+            bosses,
+            flash_material,
+            popups: vec![],
+            pc_last_dir_change: [0.0; MAX_PLAYERS],
+        })
+    }
+
+    // `inputs` holds one snapshot per player
+    pub fn update(&mut self, inputs: &[Input]) {
+        for ((ship, last_dir_change), input) in self
+            .ships
+            .iter_mut()
+            .zip(&mut self.pc_last_dir_change)
+            .zip(inputs)
+        {
+            ship.set_animation(0);
+            let left = input.left || input.stick[0] < -STICK_LEAN;
+            let right = input.right || input.stick[0] > STICK_LEAN;
+
+            // Check for direction change time
+            if !right && !left {
+                *last_dir_change = 0.0;
+            }
+
+            // Adapting animation to direction change length
+            if right || left {
+                if *last_dir_change == 0.0 {
+                    *last_dir_change = get_time() as f32;
+                }
+                let leaning = *last_dir_change > 0.0 && get_time() as f32 - *last_dir_change > 0.2;
+                let animation = match (left, leaning) {
+                    (true, true) => 2,
+                    (true, false) => 1,
+                    (false, true) => 4,
+                    (false, false) => 3,
+                };
+                ship.set_animation(animation);
+            }
+
+            ship.update();
+        }
+        self.bullet.update();
+        self.bolt.update();
+        for enemy in &mut self.enemies {
+            enemy.update();
+        }
+        for part in self.bosses.iter_mut().flatten() {
+            part.update();
+        }
+
+        for popup in &mut self.popups {
+            popup.age += get_frame_time();
+        }
+        self.popups.retain(|popup| popup.age < POPUP_TIME);
+    }
+
+    pub fn add_popup(&mut self, x: f32, y: f32, points: u32) {
+        self.popups.push(Popup {
+            text: points.to_string(),
+            position: vec2(x, y),
+            age: 0.0,
+        });
+    }
+
+    pub fn clear_popups(&mut self) {
+        self.popups.clear();
+    }
+
+    // `alpha` blends positions between the last two simulation ticks
+    pub fn draw_world(&self, world: &World, resources: &Resources, alpha: f32) {
+        // Draw power-ups
+        for powerup in &world.powerups {
+            let shape = &powerup.shape;
+            let pos = shape.lerp_position(alpha);
+            draw_rectangle(
+                pos.x - shape.size / 2.0,
+                pos.y - shape.size / 2.0,
+                shape.size,
+                shape.size,
+                Color { a: 0.35, ..shape.color },
+            );
+            draw_rectangle_lines(
+                pos.x - shape.size / 2.0,
+                pos.y - shape.size / 2.0,
+                shape.size,
+                shape.size,
+                2.0,
+                shape.color,
+            );
+            let label = &powerup.kind.label()[..1];
+            let text_dimensions = measure_text(label, None, 20, 1.0);
+            draw_text(
+                label,
+                pos.x - text_dimensions.width / 2.0,
+                pos.y + text_dimensions.offset_y / 2.0,
+                20.0,
+                WHITE,
+            );
+        }
+
+        // Draw the player's shots and the enemies'
+        for bullet in world.bullets.iter().chain(&world.hostile_bullets) {
+            let frame = match bullet.kind {
+                ProjectileKind::Bullet => self.bullet.frame(),
+                ProjectileKind::Bolt => self.bolt.frame(),
+            };
+            let shape = &bullet.shape;
+            let pos = shape.lerp_position(alpha);
+            draw_texture_ex(
+                &resources.bullet_texture,
+                pos.x - shape.size / 2.0,
+                pos.y - shape.size / 2.0,
+                shape.color,
+                DrawTextureParams {
+                    dest_size: Some(vec2(shape.size, shape.size)),
+                    source: Some(frame.source_rect),
+                    // The sheet points up
+                    rotation: bullet.velocity.to_angle() + std::f32::consts::FRAC_PI_2,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // Draw the players (ships), blinking while they can't be hurt
+        for (index, player) in world.players.iter().enumerate() {
+            let pos = player.ship.lerp_position(alpha);
+            let ship_frame = self.ships[index].frame();
+            let draw_ship = |color: Color| {
+                draw_texture_ex(
+                    &resources.ship_texture,
+                    pos.x - ship_frame.dest_size.x,
+                    pos.y - ship_frame.dest_size.y,
+                    color,
+                    DrawTextureParams {
+                        dest_size: Some(ship_frame.dest_size * 2.0),
+                        source: Some(ship_frame.source_rect),
+                        ..Default::default()
+                    },
+                );
+            };
+
+            // A downed ship's wreck, filling up while its partner revives it
+            if player.down {
+                if world.continue_timer.is_none() {
+                    draw_ship(Color { a: 0.4, ..GRAY });
+                    let revive = (player.revive / REVIVE_TIME).min(1.0);
+                    draw_rectangle_lines(pos.x - 16.0, pos.y + 28.0, 32.0, 4.0, 1.0, GRAY);
+                    draw_rectangle(pos.x - 16.0, pos.y + 28.0, 32.0 * revive, 4.0, player.ship.color);
+                }
+                continue;
+            }
+            if !player.in_play() {
+                continue;
+            }
+            if player.invulnerable <= 0.0 || (world.time * 30.0).sin() > 0.0 {
+                draw_ship(Color::from_hex(SHIP_TINTS[index]));
+            }
+            if player.effects.shield > 0.0 {
+                draw_circle_lines(pos.x, pos.y, player.ship.size, 2.0, PowerUpKind::Shield.color());
+            }
+
+            // Charge meter under the ship once a charged shot starts building
+            let charge = player.weapon.charge_fraction(&world.data.weapon);
+            if charge > 0.15 {
+                let color = if charge >= 1.0 { YELLOW } else { WHITE };
+                draw_rectangle(pos.x - 16.0, pos.y + 28.0, 32.0 * charge, 4.0, color);
+            }
+        }
+
+        // Draw the enemies
+        for enemy in &world.enemies {
+            let archetype = &world.data.enemies[enemy.kind];
+            let enemy_frame = self.enemies[enemy.kind].frame();
+            let shape = &enemy.shape;
+
+            // Flash white while recovering from a hit
+            if enemy.flash > 0.0 {
+                gl_use_material(&self.flash_material);
+            }
+            let pos = shape.lerp_position(alpha);
+            draw_texture_ex(
+                &resources.enemy_textures[&archetype.texture],
+                pos.x - shape.size / 2.0,
+                pos.y - shape.size / 2.0,
+                Color::from_hex(archetype.tint),
+                DrawTextureParams {
+                    dest_size: Some(vec2(shape.size, shape.size)),
+                    source: Some(enemy_frame.source_rect),
+                    ..Default::default()
+                },
+            );
+            if enemy.flash > 0.0 {
+                gl_use_default_material();
+            }
+        }
+
+        // Draw the boss, part by part
+        if let Some(boss) = &world.boss {
+            let def = boss.def(&world.data);
+            let center = boss.shape.lerp_position(alpha);
+            for (index, (part, part_def)) in boss.parts.iter().zip(&def.parts).enumerate() {
+                // Shot off parts are gone, except while the whole boss breaks apart
+                if !part.is_alive() && boss.state != BossState::Outro {
+                    continue;
+                }
+                let frame = self.bosses[boss.kind][index].frame();
+                if part.flash > 0.0 {
+                    gl_use_material(&self.flash_material);
+                }
+                draw_texture_ex(
+                    &resources.enemy_textures[&part_def.texture],
+                    center.x + part_def.offset.0 - part_def.size.0 / 2.0,
+                    center.y + part_def.offset.1 - part_def.size.1 / 2.0,
+                    Color::from_hex(part_def.tint),
+                    DrawTextureParams {
+                        dest_size: Some(vec2(part_def.size.0, part_def.size.1)),
+                        source: Some(frame.source_rect),
+                        ..Default::default()
+                    },
+                );
+                if part.flash > 0.0 {
+                    gl_use_default_material();
+                }
+            }
+        }
+
+        self.draw_popups();
+    }
+
+    // Countdown while the player can still take a continue
+    pub fn draw_continue(&self, world: &World, bindings: &Bindings) {
+        let Some(timer) = world.continue_timer else {
+            return;
+        };
+        let lines = [
+            (format!("CONTINUE? {}", timer.ceil().clamp(0.0, CONTINUE_TIME)), 50.0, YELLOW),
+            (
+                format!(
+                    "{} to use a credit ({} left)",
+                    (0..world.players.len())
+                        .map(|player| bindings.key_label(player, Action::Fire))
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                    world.continues
+                ),
+                25.0,
+                WHITE,
+            ),
+        ];
+        for (row, (text, size, color)) in lines.iter().enumerate() {
+            let text_dimensions = measure_text(text, None, *size as u16, 1.0);
+            draw_text(
+                text,
+                canvas::WIDTH / 2.0 - text_dimensions.width / 2.0,
+                canvas::HEIGHT / 2.0 + row as f32 * 45.0,
+                *size,
+                *color,
+            );
+        }
+    }
+
+    fn draw_popups(&self) {
+        for popup in &self.popups {
+            let t = popup.age / POPUP_TIME;
+            let text_dimensions = measure_text(&popup.text, None, 22, 1.0);
+            draw_text(
+                &popup.text,
+                popup.position.x - text_dimensions.width / 2.0,
+                popup.position.y - 30.0 * t,
+                22.0,
+                Color {
+                    a: 1.0 - t * t,
+                    ..YELLOW
+                },
+            );
+        }
+    }
+
+    // Name and remaining health of the boss across the top of the screen
+    pub fn draw_boss_bar(&self, world: &World, font: &Font) {
+        let Some(boss) = &world.boss else {
+            return;
+        };
+        if boss.state == BossState::Outro {
+            return;
+        }
+        let def = boss.def(&world.data);
+        let width = canvas::WIDTH * 0.6;
+        let x = (canvas::WIDTH - width) / 2.0;
+        let y = 60.0;
+
+        // Fill up during the intro, like the boss is powering on
+        let health = match boss.state {
+            BossState::Intro => (boss.state_time / def.intro_time.max(f32::EPSILON)).min(1.0),
+            _ => boss.health_fraction(&world.data),
+        };
+        draw_rectangle(x, y, width, 12.0, Color::from_hex(0x3a1010));
+        draw_rectangle(x, y, width * health, 12.0, Color::from_hex(0xd83834));
+        draw_rectangle_lines(x, y, width, 12.0, 2.0, WHITE);
+
+        let text_dimensions = measure_text(&def.name, Some(font), 18, 1.0);
+        draw_text_ex(
+            &def.name,
+            canvas::WIDTH / 2.0 - text_dimensions.width / 2.0,
+            y - 6.0,
+            TextParams {
+                font: Some(font),
+                font_size: 18,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn draw_scores(&self, world: &World) {
+        draw_text(
+            format!("Score: {}", world.score).as_str(),
+            10.0,
+            35.0,
+            25.0,
+            WHITE,
+        );
+
+        let highscore_text = format!("High score: {}", world.high_score);
+        let text_dimensions = measure_text(highscore_text.as_str(), None, 25, 1.0);
+        draw_text(
+            highscore_text.as_str(),
+            canvas::WIDTH - text_dimensions.width - 10.0,
+            35.0,
+            25.0,
+            YELLOW,
+        );
+
+        // Each player's share of the score in co-op
+        let mut left_y = 60.0;
+        if world.players.len() > 1 {
+            let mut x = 10.0;
+            for (index, player) in world.players.iter().enumerate() {
+                let text = format!("{}{}  ", player_tag(world, index), player.score);
+                draw_text(&text, x, left_y, 22.0, player.ship.color);
+                x += measure_text(&text, None, 22, 1.0).width;
+            }
+            left_y += 22.0;
+        }
+
+        // Gun tier and bombs in the bottom corner, the first player on top
+        let rows = world.players.len();
+        for (index, player) in world.players.iter().enumerate() {
+            draw_text(
+                &format!(
+                    "{}{} Lv{}  Bombs: {}",
+                    player_tag(world, index),
+                    world.data.weapon.name,
+                    player.weapon.tier + 1,
+                    player.weapon.bombs
+                ),
+                10.0,
+                canvas::HEIGHT - 15.0 - (rows - 1 - index) as f32 * 25.0,
+                25.0,
+                WHITE,
+            );
+        }
+
+        let mut lives_text = String::new();
+        for (index, player) in world.players.iter().enumerate() {
+            lives_text += &format!("{}Lives: {}  ", player_tag(world, index), player.lives);
+        }
+        lives_text += &format!("Credits: {}", world.continues);
+        let text_dimensions = measure_text(&lives_text, None, 25, 1.0);
+        draw_text(
+            &lives_text,
+            canvas::WIDTH - text_dimensions.width - 10.0,
+            canvas::HEIGHT - 15.0,
+            25.0,
+            WHITE,
+        );
+
+        // Chain, multiplier and graze meter under the high score
+        let combo = &world.combo;
+        let combo_text = format!("Chain {}  x{}", combo.chain, combo.multiplier());
+        let text_dimensions = measure_text(&combo_text, None, 22, 1.0);
+        let right = canvas::WIDTH - 10.0;
+        draw_text(
+            &combo_text,
+            right - text_dimensions.width,
+            60.0,
+            22.0,
+            if combo.chain > 0 { ORANGE } else { GRAY },
+        );
+        if combo.chain > 0 {
+            let width = 120.0 * (combo.timer / CHAIN_WINDOW).clamp(0.0, 1.0);
+            draw_rectangle(right - width, 66.0, width, 3.0, ORANGE);
+        }
+        draw_text("GRAZE", right - 170.0, 86.0, 18.0, SKYBLUE);
+        draw_rectangle_lines(right - 120.0, 76.0, 120.0, 10.0, 1.0, SKYBLUE);
+        draw_rectangle(right - 120.0, 76.0, 120.0 * combo.graze, 10.0, SKYBLUE);
+        for level in 0..combo.graze_level {
+            draw_circle(right - 180.0 - level as f32 * 12.0, 81.0, 4.0, SKYBLUE);
+        }
+
+        // Running power-ups with the seconds they have left
+        for (index, player) in world.players.iter().enumerate() {
+            for (kind, time) in player.effects.active() {
+                draw_text(
+                    &format!("{}{} {:.0}", player_tag(world, index), kind.label(), time.ceil()),
+                    10.0,
+                    left_y,
+                    22.0,
+                    kind.color(),
+                );
+                left_y += 22.0;
+            }
+        }
+    }
+}
+
+// "P1 ", "P2 " in front of per-player HUD lines, nothing in a single player game
+fn player_tag(world: &World, index: usize) -> String {
+    if world.players.len() > 1 {
+        format!("P{} ", index + 1)
+    } else {
+        String::new()
+    }
+}
+
+// ship.png, leaning with the direction it's steered in
+fn ship_sprite() -> AnimatedSprite {
+    AnimatedSprite::new(
+        16,
+        24,
+        &[
+            Animation {
+                name: "idle".to_string(),
+                row: 0,
+                frames: 2,
+                fps: 12,
+            },
+            Animation {
+                name: "slight_left".to_string(),
+                row: 1,
+                frames: 2,
+                fps: 12,
+            },
+            Animation {
+                name: "left".to_string(),
+                row: 2,
+                frames: 2,
+                fps: 12,
+            },
+            Animation {
+                name: "slight_right".to_string(),
+                row: 3,
+                frames: 2,
+                fps: 12,
+            },
+            Animation {
+                name: "right".to_string(),
+                row: 4,
+                frames: 2,
+                fps: 12,
+            },
+        ],
+        true,
+    )
+}
+
+// laser-bolts.png playing the given row
+fn laser_sprite(animation: usize) -> AnimatedSprite {
+    let mut sprite = AnimatedSprite::new(
+        16,
+        16,
+        &[
+            Animation {
+                name: "bullet".to_string(),
+                row: 0,
+                frames: 2,
+                fps: 12,
+            },
+            Animation {
+                name: "bolt".to_string(),
+                row: 1,
+                frames: 2,
+                fps: 12,
+            },
+        ],
+        true,
+    );
+    sprite.set_animation(animation);
+    sprite
+}
+
+// Top of the leaderboard, centered on `center_x`
+pub fn draw_leaderboard(save_data: &SaveData, center_x: f32, top: f32) {
+    for (rank, entry) in save_data.leaderboard.iter().take(5).enumerate() {
+        let line = format!(
+            "{}. {:<12} {:>6}  {:<9} {}P {}",
+            rank + 1,
+            entry.name,
+            entry.score,
+            entry.difficulty,
+            entry.players,
+            save::format_date(entry.timestamp),
+        );
+        let line_dim = measure_text(&line, None, 24, 1.0);
+        draw_text(
+            &line,
+            center_x - line_dim.width / 2.0,
+            top + rank as f32 * 26.0,
+            24.0,
+            WHITE,
+        );
+    }
+}
+
+// The rebinding screen: every action with each player's key, keys bound twice
+// in red
+pub fn draw_bindings(bindings: &Bindings, menu: &BindingsMenu, center_x: f32, touch: bool) {
+    let title = "Controls";
+    let title_dim = measure_text(title, None, 40, 1.0);
+    draw_text(title, center_x - title_dim.width / 2.0, 60.0, 40.0, WHITE);
+
+    let columns = [center_x - 150.0, center_x + 10.0, center_x + 100.0];
+    draw_text("P1", columns[1], 110.0, 24.0, GRAY);
+    draw_text("P2", columns[2], 110.0, 24.0, GRAY);
+    for (row, action) in Action::ALL.iter().enumerate() {
+        let y = 140.0 + row as f32 * 30.0;
+        let selected = row == menu.action;
+        draw_text(action.label(), columns[0], y, 24.0, if selected { YELLOW } else { WHITE });
+        for player in 0..MAX_PLAYERS {
+            let text = if selected && player == menu.player && menu.capturing {
+                "...".to_string()
+            } else {
+                bindings.key_label(player, *action)
+            };
+            let clash = bindings
+                .key(player, *action)
+                .is_some_and(|key| bindings.conflict(player, *action, key).is_some());
+            let color = match (clash, selected && player == menu.player) {
+                (true, _) => RED,
+                (false, true) => YELLOW,
+                (false, false) => WHITE,
+            };
+            draw_text(&text, columns[player + 1], y, 24.0, color);
+        }
+    }
+
+    let bottom = 160.0 + Action::ALL.len() as f32 * 30.0;
+    let prompt = if menu.capturing {
+        "Press a key for this action, [Escape] or B to cancel".to_string()
+    } else if let Some(message) = &menu.message {
+        message.clone()
+    } else {
+        String::new()
+    };
+    let prompt_dim = measure_text(&prompt, None, 24, 1.0);
+    draw_text(&prompt, center_x - prompt_dim.width / 2.0, bottom, 24.0, ORANGE);
+
+    let help: &[&str] = if touch {
+        touch::draw_button(touch::back_button(), "BACK", false);
+        &["Rebinding keys needs a keyboard"]
+    } else {
+        &[
+            "[Up]/[Down] pick an action, [Left]/[Right] a player",
+            "[Enter] to rebind, [Backspace] restores the defaults",
+            "[Escape] to go back",
+        ]
+    };
+    for (line, text) in help.iter().enumerate() {
+        let text_dim = measure_text(text, None, 20, 1.0);
+        draw_text(
+            text,
+            center_x - text_dim.width / 2.0,
+            bottom + 40.0 + line as f32 * 24.0,
+            20.0,
+            GRAY,
+        );
+    }
+}
+
+// The settings screen, one option per row, drawn as buttons on a touch screen
+pub fn draw_settings(save_data: &SaveData, menu: &SettingsMenu, center_x: f32, touch: bool) {
+    let title = "Settings";
+    let title_dim = measure_text(title, None, 40, 1.0);
+    draw_text(title, center_x - title_dim.width / 2.0, 60.0, 40.0, WHITE);
+
+    for (row, setting) in Setting::ALL.iter().enumerate() {
+        let rect = settings::row_rect(row);
+        if touch {
+            touch::draw_button(rect, "", row == menu.row);
+        }
+        let y = rect.y + rect.h / 2.0 + 8.0;
+        let color = if row == menu.row { YELLOW } else { WHITE };
+        draw_text(setting.label(), center_x - 150.0, y, 24.0, color);
+        draw_text(setting.value_label(save_data), center_x + 40.0, y, 24.0, color);
+    }
+
+    let help: &[&str] = if touch {
+        touch::draw_button(touch::back_button(), "BACK", false);
+        &["Tap a setting to change it"]
+    } else {
+        &["[Up]/[Down] pick a setting, [Enter] changes it", "[Escape] to go back"]
+    };
+    let bottom = settings::row_rect(Setting::ALL.len()).y + 20.0;
+    for (line, text) in help.iter().enumerate() {
+        let text_dim = measure_text(text, None, 20, 1.0);
+        draw_text(
+            text,
+            center_x - text_dim.width / 2.0,
+            bottom + line as f32 * 24.0,
+            20.0,
+            GRAY,
+        );
+    }
+}
@@ -0,0 +1,452 @@
+use crate::input::Input;
+use crate::player::MAX_PLAYERS;
+use crate::storage::Storage;
+use crate::timestep::TICK_RATE;
+use std::{fmt, io};
+
+// Binary layout (little endian):
+//   magic "AGRP", version u8, seed u64, width f32, height f32,
+//   difficulty name length u8, difficulty name (utf-8), players u8,
+//   final tick u32, final score u32, run count u32,
+//   then runs of (input per player, length u16)
+// where each input is its bits u8, followed by the stick as two i8 when bit 7
+// is set
+const MAGIC: &[u8; 4] = b"AGRP";
+// Bumped whenever the simulation changes, since older runs would play out
+// differently
+const VERSION: u8 = 5;
+const STICK_BIT: u8 = 1 << 7;
+// Longest run kept, three hours of ticks, so a small file can't claim
+// billions of them
+const MAX_TICKS: usize = 3 * 60 * 60 * TICK_RATE as usize;
+
+pub const LAST_REPLAY_FILE: &str = "last.replay";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    BadPlayerCount(u8),
+    Truncated,
+    TooLong,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not read replay: {}", err),
+            ReplayError::BadMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(v) => write!(f, "unsupported replay version {}", v),
+            ReplayError::BadPlayerCount(n) => write!(f, "unsupported number of players {}", n),
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+            ReplayError::TooLong => write!(f, "replay is longer than {} ticks", MAX_TICKS),
+        }
+    }
+}
+
+// Everything needed to re-simulate a run: the seed, the playfield and every
+// player's input for every tick
+#[derive(Clone, Debug, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub width: f32,
+    pub height: f32,
+    // Name of the difficulty preset
+    pub difficulty: String,
+    pub players: usize,
+    pub inputs: Vec<[Input; MAX_PLAYERS]>,
+    pub final_tick: u32,
+    pub final_score: u32,
+}
+
+impl Replay {
+    pub fn new(seed: u64, width: f32, height: f32, difficulty: &str, players: usize) -> Self {
+        Replay {
+            seed,
+            width,
+            height,
+            difficulty: difficulty.to_string(),
+            players,
+            ..Default::default()
+        }
+    }
+
+    // Runs past the longest replay are only recorded up to it
+    pub fn record(&mut self, inputs: [Input; MAX_PLAYERS]) {
+        if self.inputs.len() < MAX_TICKS {
+            self.inputs.push(inputs);
+        }
+    }
+
+    pub fn finish(&mut self, tick: u32, score: u32) {
+        self.final_tick = tick;
+        self.final_score = score;
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Collapse identical consecutive inputs into runs, ignoring the
+        // players who weren't in the game
+        let players = self.players.clamp(1, MAX_PLAYERS);
+        let mut runs: Vec<([Input; MAX_PLAYERS], u16)> = vec![];
+        for inputs in &self.inputs {
+            let mut frame = [Input::default(); MAX_PLAYERS];
+            frame[..players].copy_from_slice(&inputs[..players]);
+            match runs.last_mut() {
+                Some((last, len)) if *last == frame && *len < u16::MAX => *len += 1,
+                _ => runs.push((frame, 1)),
+            }
+        }
+
+        // The name's length has to fit in a byte, cut on a character boundary
+        let mut len = self.difficulty.len().min(u8::MAX as usize);
+        while !self.difficulty.is_char_boundary(len) {
+            len -= 1;
+        }
+        let difficulty = &self.difficulty.as_bytes()[..len];
+        let mut bytes = Vec::with_capacity(31 + difficulty.len() + runs.len() * (players + 2));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.push(difficulty.len() as u8);
+        bytes.extend_from_slice(difficulty);
+        bytes.push(players as u8);
+        bytes.extend_from_slice(&self.final_tick.to_le_bytes());
+        bytes.extend_from_slice(&self.final_score.to_le_bytes());
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (frame, len) in runs {
+            for input in &frame[..players] {
+                write_input(&mut bytes, input);
+            }
+            bytes.extend_from_slice(&len.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn save(&self, storage: &dyn Storage, key: &str) -> io::Result<()> {
+        storage.write(key, &self.to_bytes())
+    }
+
+    pub fn load(storage: &dyn Storage, key: &str) -> Result<Replay, ReplayError> {
+        let bytes = storage.read(key).map_err(ReplayError::Io)?;
+        Replay::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, ReplayError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let mut replay = Replay {
+            seed: u64::from_le_bytes(reader.array()?),
+            width: f32::from_le_bytes(reader.array()?),
+            height: f32::from_le_bytes(reader.array()?),
+            difficulty: {
+                let len = reader.take(1)?[0] as usize;
+                String::from_utf8_lossy(reader.take(len)?).into_owned()
+            },
+            players: match reader.take(1)?[0] {
+                players if (1..=MAX_PLAYERS).contains(&(players as usize)) => players as usize,
+                players => return Err(ReplayError::BadPlayerCount(players)),
+            },
+            final_tick: u32::from_le_bytes(reader.array()?),
+            final_score: u32::from_le_bytes(reader.array()?),
+            inputs: vec![],
+        };
+        // Every run takes at least a byte per player and its length
+        let run_count = u32::from_le_bytes(reader.array()?) as usize;
+        if run_count.saturating_mul(replay.players + 2) > reader.remaining() {
+            return Err(ReplayError::Truncated);
+        }
+        for _ in 0..run_count {
+            let mut inputs = [Input::default(); MAX_PLAYERS];
+            for input in &mut inputs[..replay.players] {
+                *input = read_input(&mut reader)?;
+            }
+            let len = u16::from_le_bytes(reader.array()?);
+            if replay.inputs.len() + len as usize > MAX_TICKS {
+                return Err(ReplayError::TooLong);
+            }
+            replay
+                .inputs
+                .extend(std::iter::repeat_n(inputs, len as usize));
+        }
+        Ok(replay)
+    }
+}
+
+// Feeds a recorded run back into the simulation one tick at a time
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    tick: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer { replay, tick: 0 }
+    }
+
+    pub fn next_input(&mut self) -> Option<[Input; MAX_PLAYERS]> {
+        let input = self.replay.inputs.get(self.tick).copied();
+        self.tick += 1;
+        input
+    }
+
+    // Compare the re-simulated outcome against what was recorded
+    pub fn verify(&self, tick: u32, score: u32) -> bool {
+        self.replay.final_tick == tick && self.replay.final_score == score
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        let slice = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(ReplayError::Truncated)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+}
+
+fn write_input(bytes: &mut Vec<u8>, input: &Input) {
+    if input.stick == [0, 0] {
+        bytes.push(input_to_bits(input));
+    } else {
+        bytes.push(input_to_bits(input) | STICK_BIT);
+        bytes.extend(input.stick.map(|axis| axis as u8));
+    }
+}
+
+fn read_input(reader: &mut Reader) -> Result<Input, ReplayError> {
+    let bits = reader.take(1)?[0];
+    let mut input = bits_to_input(bits);
+    if bits & STICK_BIT != 0 {
+        let stick: [u8; 2] = reader.array()?;
+        input.stick = stick.map(|axis| axis as i8);
+    }
+    Ok(input)
+}
+
+fn input_to_bits(input: &Input) -> u8 {
+    input.left as u8
+        | (input.right as u8) << 1
+        | (input.up as u8) << 2
+        | (input.down as u8) << 3
+        | (input.fire as u8) << 4
+        | (input.pause as u8) << 5
+        | (input.bomb as u8) << 6
+}
+
+fn bits_to_input(bits: u8) -> Input {
+    Input {
+        left: bits & 1 != 0,
+        right: bits & 1 << 1 != 0,
+        up: bits & 1 << 2 != 0,
+        down: bits & 1 << 3 != 0,
+        fire: bits & 1 << 4 != 0,
+        pause: bits & 1 << 5 != 0,
+        bomb: bits & 1 << 6 != 0,
+        stick: [0, 0],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+    use crate::timestep::TICK;
+    use crate::world::{World, WorldEvent};
+
+    // Inputs that change every so often, with some stick thrown in
+    fn scripted(tick: usize) -> [Input; MAX_PLAYERS] {
+        std::array::from_fn(|player| {
+            let phase = (tick / 37 + player * 3) % 8;
+            let mut input = Input {
+                left: phase == 1 || phase == 2,
+                right: phase == 5,
+                up: phase == 3,
+                down: phase == 6,
+                fire: !phase.is_multiple_of(3),
+                bomb: tick % 1500 == 700,
+                pause: false,
+                stick: [0, 0],
+            };
+            if phase == 7 {
+                input.stick = [-90, 40];
+            }
+            input
+        })
+    }
+
+    // Play `ticks` ticks of a fresh run, stopping at game over
+    fn record(seed: u64, players: usize, ticks: usize) -> Replay {
+        let data = data::bundled();
+        let mut replay = Replay::new(seed, 800.0, 600.0, "Normal", players);
+        let difficulty = data.difficulty_index(&replay.difficulty).unwrap();
+        let mut world = World::new(data, 800.0, 600.0, 0, seed, difficulty, players);
+        for tick in 0..ticks {
+            let inputs = scripted(tick);
+            replay.record(inputs);
+            if world.step(TICK, &inputs).contains(&WorldEvent::GameOver) {
+                break;
+            }
+        }
+        replay.finish(world.tick, world.score);
+        replay
+    }
+
+    // Re-simulate `replay` the way the game plays one back
+    fn play_back(replay: Replay) -> (ReplayPlayer, u32, u32) {
+        let data = data::bundled();
+        let difficulty = data.difficulty_index(&replay.difficulty).unwrap();
+        let mut world = World::new(
+            data,
+            replay.width,
+            replay.height,
+            0,
+            replay.seed,
+            difficulty,
+            replay.players,
+        );
+        let mut player = ReplayPlayer::new(replay);
+        while let Some(inputs) = player.next_input() {
+            if world.step(TICK, &inputs).contains(&WorldEvent::GameOver) {
+                break;
+            }
+        }
+        (player, world.tick, world.score)
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let mut replay = Replay::new(0xdead_beef_1234, 800.0, 600.0, "Hard", 2);
+        for tick in 0..5000 {
+            replay.record(scripted(tick));
+        }
+        // Longer than one run can hold
+        replay
+            .inputs
+            .extend(std::iter::repeat_n([Input::default(); MAX_PLAYERS], 70000));
+        replay.finish(75000, 12345);
+
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.width, replay.width);
+        assert_eq!(loaded.height, replay.height);
+        assert_eq!(loaded.difficulty, replay.difficulty);
+        assert_eq!(loaded.players, replay.players);
+        assert_eq!(loaded.final_tick, replay.final_tick);
+        assert_eq!(loaded.final_score, replay.final_score);
+        assert_eq!(loaded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn single_player_drops_the_unused_input() {
+        let mut replay = Replay::new(1, 800.0, 600.0, "Easy", 1);
+        replay.record(scripted(0));
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.inputs[0][0], replay.inputs[0][0]);
+        assert_eq!(loaded.inputs[0][1], Input::default());
+    }
+
+    #[test]
+    fn long_difficulty_name_is_cut_between_characters() {
+        // 127 two-byte characters then one more, past the 255 byte limit
+        let name = "é".repeat(128);
+        let replay = Replay::new(1, 800.0, 600.0, &name, 1);
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.difficulty, "é".repeat(127));
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let bytes = Replay::new(1, 800.0, 600.0, "Normal", 1).to_bytes();
+        assert!(matches!(Replay::from_bytes(b"nope"), Err(ReplayError::BadMagic)));
+
+        let mut old = bytes.clone();
+        old[4] = VERSION - 1;
+        assert!(matches!(
+            Replay::from_bytes(&old),
+            Err(ReplayError::UnsupportedVersion(_))
+        ));
+
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ReplayError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_headers() {
+        let mut replay = Replay::new(1, 800.0, 600.0, "Normal", 1);
+        replay.record([Input::default(); MAX_PLAYERS]);
+        let bytes = replay.to_bytes();
+        let runs_at = bytes.len() - 3 - 4;
+
+        // Claims far more runs than the file holds
+        let mut claimed = bytes.clone();
+        claimed[runs_at..runs_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(Replay::from_bytes(&claimed), Err(ReplayError::Truncated)));
+
+        // Every run as long as it gets, adding up past the longest replay
+        let count = MAX_TICKS / u16::MAX as usize + 1;
+        let mut long = bytes[..runs_at].to_vec();
+        long.extend_from_slice(&(count as u32).to_le_bytes());
+        for _ in 0..count {
+            long.push(0);
+            long.extend_from_slice(&u16::MAX.to_le_bytes());
+        }
+        assert!(matches!(Replay::from_bytes(&long), Err(ReplayError::TooLong)));
+    }
+
+    #[test]
+    fn recording_stops_at_the_longest_replay() {
+        let mut replay = Replay::new(1, 800.0, 600.0, "Normal", 1);
+        replay.inputs = vec![[Input::default(); MAX_PLAYERS]; MAX_TICKS];
+        replay.record(scripted(0));
+        assert_eq!(replay.inputs.len(), MAX_TICKS);
+        assert!(Replay::from_bytes(&replay.to_bytes()).is_ok());
+    }
+
+    #[test]
+    fn playback_reproduces_the_run() {
+        for (seed, players) in [(7, 1), (42, 2)] {
+            let replay = record(seed, players, 120 * 60);
+            assert!(replay.final_score > 0);
+            let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+            let (player, tick, score) = play_back(loaded);
+            assert!(player.verify(tick, score));
+        }
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let first = record(99, 1, 120 * 30);
+        let second = record(99, 1, 120 * 30);
+        assert_eq!(first.final_tick, second.final_tick);
+        assert_eq!(first.final_score, second.final_score);
+    }
+}
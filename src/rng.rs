@@ -0,0 +1,40 @@
+// Small seedable PCG32 generator owned by the simulation.
+// macroquad's `rand` is a single global that the particle emitters also draw
+// from, so runs using it can never be reproduced tick for tick.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng {
+            state: seed.wrapping_add(0x9e37_79b9_7f4a_7c15),
+        };
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Uniform float in `low..high`
+    pub fn gen_range(&mut self, low: f32, high: f32) -> f32 {
+        let unit = (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32;
+        low + (high - low) * unit
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.next_u32() as usize % items.len())
+    }
+}
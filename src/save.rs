@@ -0,0 +1,366 @@
+use crate::difficulty::DEFAULT_DIFFICULTY;
+use crate::storage::{self, Storage};
+use macroquad::prelude::warn;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+pub const SAVE_FILE: &str = "save.ron";
+// Bare integer written by versions before the save file existed
+const LEGACY_HIGHSCORE_FILE: &str = "highscore.dat";
+
+// Bump when the layout of `SaveData` changes, and teach `migrate` about it
+pub const SAVE_VERSION: u32 = 2;
+pub const LEADERBOARD_SIZE: usize = 10;
+pub const MAX_NAME_LEN: usize = 12;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u32,
+    // Seconds since the unix epoch
+    pub timestamp: u64,
+    pub seed: u64,
+    // Length of the run in seconds
+    pub duration: f32,
+    // Name of the difficulty preset the run was played on
+    #[serde(default = "default_difficulty")]
+    pub difficulty: String,
+    // Ships in the run, 2 for co-op
+    #[serde(default = "default_players")]
+    pub players: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub last_name: String,
+    // Preset picked in the main menu last time
+    #[serde(default = "default_difficulty")]
+    pub difficulty: String,
+    // Scale the playfield by whole multiples only
+    #[serde(default)]
+    pub integer_scaling: bool,
+    // Post-processing passes run over the frame
+    #[serde(default)]
+    pub bloom: bool,
+    #[serde(default)]
+    pub chromatic_aberration: bool,
+    #[serde(default)]
+    pub scanlines: bool,
+    #[serde(default)]
+    pub crt: bool,
+    // Camera effects, which can be turned off for comfort
+    #[serde(default = "default_on")]
+    pub screen_shake: bool,
+    #[serde(default = "default_on")]
+    pub hit_stop: bool,
+    #[serde(default = "default_on")]
+    pub boss_zoom: bool,
+    // The first ship follows the mouse and fires with the left button
+    #[serde(default)]
+    pub mouse_control: bool,
+    pub leaderboard: Vec<ScoreEntry>,
+}
+
+// Saves from before difficulty presets were all played on Normal
+fn default_difficulty() -> String {
+    DEFAULT_DIFFICULTY.to_string()
+}
+
+// Saves from before co-op were all single player
+fn default_players() -> usize {
+    1
+}
+
+// Settings added after the save file default to on
+fn default_on() -> bool {
+    true
+}
+
+// Just enough of the file to find out which layout it uses
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            last_name: "Player".to_string(),
+            difficulty: default_difficulty(),
+            integer_scaling: false,
+            bloom: false,
+            chromatic_aberration: false,
+            scanlines: false,
+            crt: false,
+            screen_shake: true,
+            hit_stop: true,
+            boss_zoom: true,
+            mouse_control: false,
+            leaderboard: vec![],
+        }
+    }
+}
+
+impl SaveData {
+    pub fn load(storage: &dyn Storage) -> SaveData {
+        storage::load_or_back_up(storage, SAVE_FILE, SaveData::migrate)
+            .unwrap_or_else(|| SaveData::from_legacy(storage))
+    }
+
+    fn migrate(text: &str) -> Result<SaveData, String> {
+        let probe: VersionProbe = ron::from_str(text).map_err(|err| err.to_string())?;
+        match probe.version {
+            SAVE_VERSION => ron::from_str(text).map_err(|err| err.to_string()),
+            // Version 1 only lacks the difficulty fields, which have defaults
+            1 => {
+                let mut save: SaveData = ron::from_str(text).map_err(|err| err.to_string())?;
+                save.version = SAVE_VERSION;
+                Ok(save)
+            }
+            version => Err(format!("unknown save version {}", version)),
+        }
+    }
+
+    fn from_legacy(storage: &dyn Storage) -> SaveData {
+        let mut save = SaveData::default();
+        let legacy_score = storage
+            .read_to_string(LEGACY_HIGHSCORE_FILE)
+            .ok()
+            .and_then(|text| text.trim().parse::<u32>().ok());
+        if let Some(score) = legacy_score {
+            save.leaderboard.push(ScoreEntry {
+                name: save.last_name.clone(),
+                score,
+                timestamp: 0,
+                seed: 0,
+                duration: 0.0,
+                difficulty: default_difficulty(),
+                players: default_players(),
+            });
+            if let Err(err) = save.save(storage) {
+                warn!("Could not migrate {}: {}", LEGACY_HIGHSCORE_FILE, err);
+            }
+        }
+        save
+    }
+
+    pub fn save(&self, storage: &dyn Storage) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        storage.write(SAVE_FILE, text.as_bytes())
+    }
+
+    pub fn high_score(&self) -> u32 {
+        self.leaderboard.first().map_or(0, |entry| entry.score)
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.leaderboard.len() < LEADERBOARD_SIZE
+                || self.leaderboard.iter().any(|entry| score > entry.score))
+    }
+
+    // Insert keeping the board sorted, returning the entry's rank
+    pub fn insert(&mut self, entry: ScoreEntry) -> Option<usize> {
+        let rank = self
+            .leaderboard
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.leaderboard.len());
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+        self.last_name = entry.name.clone();
+        self.leaderboard.insert(rank, entry);
+        self.leaderboard.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+
+    // Put the name the player typed on the entry at `rank`
+    pub fn rename(&mut self, rank: usize, name: &str) {
+        if let Some(entry) = self.leaderboard.get_mut(rank) {
+            entry.name = name.to_string();
+        }
+        self.last_name = name.to_string();
+    }
+}
+
+// YYYY-MM-DD for a unix timestamp, in UTC
+pub fn format_date(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "----------".to_string();
+    }
+    // Days to civil date, from Howard Hinnant's algorithm
+    let z = (timestamp / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn entry(name: &str, score: u32) -> ScoreEntry {
+        ScoreEntry {
+            name: name.to_string(),
+            score,
+            timestamp: 1,
+            seed: 2,
+            duration: 3.0,
+            difficulty: default_difficulty(),
+            players: 1,
+        }
+    }
+
+    fn scores(save: &SaveData) -> Vec<u32> {
+        save.leaderboard.iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn migrates_legacy_high_score() {
+        let storage = MemoryStorage::default();
+        storage.write(LEGACY_HIGHSCORE_FILE, b"4200\n").unwrap();
+
+        let save = SaveData::load(&storage);
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(scores(&save), vec![4200]);
+        assert_eq!(save.leaderboard[0].name, save.last_name);
+        assert_eq!(save.leaderboard[0].difficulty, DEFAULT_DIFFICULTY);
+
+        // Written out in the current layout, so the old file is never read again
+        storage.write(LEGACY_HIGHSCORE_FILE, b"1").unwrap();
+        let reloaded = SaveData::load(&storage);
+        assert_eq!(scores(&reloaded), vec![4200]);
+    }
+
+    #[test]
+    fn no_save_at_all_starts_empty() {
+        let storage = MemoryStorage::default();
+        let save = SaveData::load(&storage);
+        assert!(save.leaderboard.is_empty());
+        assert!(storage.read(SAVE_FILE).is_err());
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let storage = MemoryStorage::default();
+        let v1 = r#"(
+            version: 1,
+            last_name: "Ace",
+            leaderboard: [
+                (name: "Ace", score: 900, timestamp: 10, seed: 20, duration: 30.0),
+                (name: "Bee", score: 500, timestamp: 11, seed: 21, duration: 31.0),
+            ],
+        )"#;
+        storage.write(SAVE_FILE, v1.as_bytes()).unwrap();
+
+        let save = SaveData::load(&storage);
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.last_name, "Ace");
+        assert_eq!(save.difficulty, DEFAULT_DIFFICULTY);
+        assert!(save.screen_shake && save.hit_stop && save.boss_zoom);
+        assert!(!save.bloom && !save.integer_scaling);
+        assert_eq!(scores(&save), vec![900, 500]);
+        for entry in &save.leaderboard {
+            assert_eq!(entry.difficulty, DEFAULT_DIFFICULTY);
+            assert_eq!(entry.players, 1);
+        }
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let storage = MemoryStorage::default();
+        let mut save = SaveData {
+            difficulty: "Hard".to_string(),
+            crt: true,
+            hit_stop: false,
+            ..Default::default()
+        };
+        save.insert(entry("Ace", 100));
+        save.save(&storage).unwrap();
+
+        let loaded = SaveData::load(&storage);
+        assert_eq!(loaded.difficulty, "Hard");
+        assert!(loaded.crt && !loaded.hit_stop);
+        assert_eq!(scores(&loaded), vec![100]);
+    }
+
+    #[test]
+    fn unknown_version_is_backed_up() {
+        let storage = MemoryStorage::default();
+        let text = "(version: 99, last_name: \"Ace\", leaderboard: [])";
+        storage.write(SAVE_FILE, text.as_bytes()).unwrap();
+
+        let save = SaveData::load(&storage);
+        assert!(save.leaderboard.is_empty());
+        assert_eq!(storage.read_to_string(&format!("{}.bak", SAVE_FILE)).unwrap(), text);
+    }
+
+    #[test]
+    fn insert_keeps_the_board_sorted() {
+        let mut save = SaveData::default();
+        assert_eq!(save.insert(entry("a", 300)), Some(0));
+        assert_eq!(save.insert(entry("b", 500)), Some(0));
+        assert_eq!(save.insert(entry("c", 100)), Some(2));
+        assert_eq!(save.insert(entry("d", 400)), Some(1));
+        assert_eq!(scores(&save), vec![500, 400, 300, 100]);
+        assert_eq!(save.high_score(), 500);
+        assert_eq!(save.last_name, "d");
+    }
+
+    #[test]
+    fn ties_go_below_the_earlier_score() {
+        let mut save = SaveData::default();
+        save.insert(entry("first", 200));
+        assert_eq!(save.insert(entry("second", 200)), Some(1));
+        assert_eq!(save.leaderboard[0].name, "first");
+    }
+
+    #[test]
+    fn insert_truncates_to_the_board_size() {
+        let mut save = SaveData::default();
+        for score in 1..=LEADERBOARD_SIZE as u32 {
+            save.insert(entry("a", score * 10));
+        }
+        assert_eq!(save.leaderboard.len(), LEADERBOARD_SIZE);
+        assert!(!save.qualifies(10));
+        assert!(save.qualifies(15));
+
+        // Too low to make it on, and nothing changes
+        assert_eq!(save.insert(entry("low", 5)), None);
+        assert_eq!(save.last_name, "a");
+        assert_eq!(save.leaderboard.len(), LEADERBOARD_SIZE);
+
+        // The lowest score drops off the bottom
+        assert_eq!(save.insert(entry("mid", 55)), Some(5));
+        assert_eq!(save.leaderboard.len(), LEADERBOARD_SIZE);
+        assert_eq!(save.leaderboard.last().unwrap().score, 20);
+    }
+
+    #[test]
+    fn zero_never_qualifies() {
+        assert!(!SaveData::default().qualifies(0));
+    }
+
+    #[test]
+    fn rename_names_the_saved_entry() {
+        let mut save = SaveData::default();
+        save.insert(entry("a", 300));
+        let rank = save.insert(entry("Player", 200)).unwrap();
+        save.rename(rank, "Zed");
+        assert_eq!(save.leaderboard[1].name, "Zed");
+        assert_eq!(save.leaderboard[0].name, "a");
+        assert_eq!(save.last_name, "Zed");
+    }
+}
+
@@ -0,0 +1,23 @@
+#version 100
+
+// Dark gaps between the rows of the picture
+
+precision mediump float;
+
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 iResolution;
+
+// How dark the gaps get
+#define INTENSITY .3
+// Canvas pixels per scanline
+#define LINE_HEIGHT 2.
+
+void main() {
+    vec3 col = texture2D(Texture, uv).rgb;
+    float line = sin(uv.y * iResolution.y / LINE_HEIGHT * 3.14159);
+    col *= 1. - INTENSITY * (1. - line * line);
+
+    gl_FragColor = vec4(col, 1.);
+}
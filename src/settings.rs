@@ -0,0 +1,158 @@
+use crate::canvas;
+use crate::gamepad::MenuInput;
+use crate::postfx::Pass;
+use crate::save::SaveData;
+use crate::storage::Storage;
+use crate::touch::{self, TouchControls};
+use macroquad::prelude::*;
+
+// Rows are tall enough to tap
+pub const ROW_HEIGHT: f32 = 48.0;
+const ROWS_TOP: f32 = 100.0;
+const ROW_WIDTH: f32 = 400.0;
+
+// Everything on the settings screen, each kept in the save file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Setting {
+    IntegerScaling,
+    Bloom,
+    ChromaticAberration,
+    Scanlines,
+    Crt,
+    ScreenShake,
+    HitStop,
+    BossZoom,
+    MouseControl,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 9] = [
+        Setting::IntegerScaling,
+        Setting::Bloom,
+        Setting::ChromaticAberration,
+        Setting::Scanlines,
+        Setting::Crt,
+        Setting::ScreenShake,
+        Setting::HitStop,
+        Setting::BossZoom,
+        Setting::MouseControl,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::IntegerScaling => "Scaling",
+            Setting::Bloom => "Bloom",
+            Setting::ChromaticAberration => "Color fringing",
+            Setting::Scanlines => "Scanlines",
+            Setting::Crt => "CRT curvature",
+            Setting::ScreenShake => "Screen shake",
+            Setting::HitStop => "Hit-stop",
+            Setting::BossZoom => "Boss intro zoom",
+            Setting::MouseControl => "Mouse control",
+        }
+    }
+
+    pub fn value_label(self, save_data: &SaveData) -> &'static str {
+        match self {
+            Setting::IntegerScaling if save_data.integer_scaling => "Pixel perfect",
+            Setting::IntegerScaling => "Smooth",
+            _ if self.enabled(save_data) => "On",
+            _ => "Off",
+        }
+    }
+
+    pub fn enabled(self, save_data: &SaveData) -> bool {
+        match self {
+            Setting::IntegerScaling => save_data.integer_scaling,
+            Setting::Bloom => save_data.bloom,
+            Setting::ChromaticAberration => save_data.chromatic_aberration,
+            Setting::Scanlines => save_data.scanlines,
+            Setting::Crt => save_data.crt,
+            Setting::ScreenShake => save_data.screen_shake,
+            Setting::HitStop => save_data.hit_stop,
+            Setting::BossZoom => save_data.boss_zoom,
+            Setting::MouseControl => save_data.mouse_control,
+        }
+    }
+
+    fn toggle(self, save_data: &mut SaveData) {
+        let value = match self {
+            Setting::IntegerScaling => &mut save_data.integer_scaling,
+            Setting::Bloom => &mut save_data.bloom,
+            Setting::ChromaticAberration => &mut save_data.chromatic_aberration,
+            Setting::Scanlines => &mut save_data.scanlines,
+            Setting::Crt => &mut save_data.crt,
+            Setting::ScreenShake => &mut save_data.screen_shake,
+            Setting::HitStop => &mut save_data.hit_stop,
+            Setting::BossZoom => &mut save_data.boss_zoom,
+            Setting::MouseControl => &mut save_data.mouse_control,
+        };
+        *value = !*value;
+    }
+}
+
+// The setting that turns a post-processing pass on
+impl From<Pass> for Setting {
+    fn from(pass: Pass) -> Self {
+        match pass {
+            Pass::Bloom => Setting::Bloom,
+            Pass::ChromaticAberration => Setting::ChromaticAberration,
+            Pass::Scanlines => Setting::Scanlines,
+            Pass::Crt => Setting::Crt,
+        }
+    }
+}
+
+// Where a setting's row sits on the canvas, for drawing and tapping
+pub fn row_rect(row: usize) -> Rect {
+    Rect::new(
+        (canvas::WIDTH - ROW_WIDTH) / 2.0,
+        ROWS_TOP + row as f32 * ROW_HEIGHT,
+        ROW_WIDTH,
+        ROW_HEIGHT - 6.0,
+    )
+}
+
+// Cursor of the settings screen
+#[derive(Default)]
+pub struct SettingsMenu {
+    pub row: usize,
+}
+
+impl SettingsMenu {
+    // Handle this frame's input; returns true when the player leaves the screen
+    pub fn update(
+        &mut self,
+        save_data: &mut SaveData,
+        storage: &dyn Storage,
+        menu: MenuInput,
+        touch: &TouchControls,
+    ) -> bool {
+        let rows = Setting::ALL.len();
+        // Tapping a row picks and changes it in one go
+        let tapped = (0..rows).find(|&row| touch.tapped_on(&row_rect(row)));
+        if let Some(row) = tapped {
+            self.row = row;
+        }
+        if is_key_pressed(KeyCode::Up) || menu.up {
+            self.row = (self.row + rows - 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Down) || menu.down {
+            self.row = (self.row + 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Enter)
+            || is_key_pressed(KeyCode::Left)
+            || is_key_pressed(KeyCode::Right)
+            || menu.confirm
+            || menu.left
+            || menu.right
+            || tapped.is_some()
+        {
+            Setting::ALL[self.row].toggle(save_data);
+            if let Err(err) = save_data.save(storage) {
+                error!("Could not save settings: {}", err);
+            }
+        }
+        is_key_pressed(KeyCode::Escape) || menu.back || touch.tapped_on(&touch::back_button())
+    }
+}
@@ -1,19 +1,17 @@
-use macroquad::{color::Color, math::Rect};
+use macroquad::{color::Color, math::{vec2, Rect, Vec2}};
 
 pub struct Shape {
     pub size: f32,
     pub speed: f32,
     pub x: f32,
     pub y: f32,
+    pub prev_x: f32,
+    pub prev_y: f32,
     pub color: Color,
     pub collided: bool,
 }
     
 impl Shape {
-    pub fn collides_with(&self, other: &Self) -> bool {
-        self.rect().overlaps(&other.rect())
-    }
-
     pub fn rect(&self) -> Rect {
         Rect {
             x: self.x - self.size / 2.0,
@@ -22,4 +20,18 @@ impl Shape {
             h: self.size,
         }
     }
-}        
\ No newline at end of file
+
+    // Remember where the shape was before the next simulation tick moves it
+    pub fn store_position(&mut self) {
+        self.prev_x = self.x;
+        self.prev_y = self.y;
+    }
+
+    // Position blended between the previous and current tick for rendering
+    pub fn lerp_position(&self, alpha: f32) -> Vec2 {
+        vec2(
+            self.prev_x + (self.x - self.prev_x) * alpha,
+            self.prev_y + (self.y - self.prev_y) * alpha,
+        )
+    }
+}        
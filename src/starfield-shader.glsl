@@ -13,8 +13,11 @@ varying float iTime;
 
 uniform vec2 iResolution;
 uniform float direction_modifier;
+// Depth layers of stars, up to MAX_LAYERS
+uniform float num_layers;
 
-#define NUM_LAYERS 4.
+// Loops need a constant bound on WebGL
+#define MAX_LAYERS 16
 
 mat2 Rot(float a) {
     float s = sin(a), c = cos(a);
@@ -76,7 +79,11 @@ void main()
     uv += direction;
     vec3 col = vec3(0);
 
-    for (float i = 0.; i < 1.; i += 1. / NUM_LAYERS) {
+    for (int layer = 0; layer < MAX_LAYERS; layer++) {
+        if (float(layer) >= num_layers) {
+            break;
+        }
+        float i = float(layer) / num_layers;
         float depth = fract(i+t);
         float scale = mix(20., .5, depth);
         float fade = depth * smoothstep(1., .9, depth);
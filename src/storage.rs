@@ -0,0 +1,114 @@
+use macroquad::prelude::error;
+use std::io;
+
+// Where save data, replays and settings are persisted. On desktop and
+// Android that's the file system, on the web it's the browser's localStorage.
+pub trait Storage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>>;
+    fn write(&self, key: &str, data: &[u8]) -> io::Result<()>;
+
+    fn read_to_string(&self, key: &str) -> io::Result<String> {
+        String::from_utf8(self.read(key)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+// Parse what's stored under `key`, None if there's nothing. Data that doesn't
+// parse gives the default, and is copied to `<key>.bak` first so saving the
+// default can't lose it.
+pub fn load_or_back_up<T: Default>(
+    storage: &dyn Storage,
+    key: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Option<T> {
+    let text = storage.read_to_string(key).ok()?;
+    Some(parse(&text).unwrap_or_else(|err| {
+        error!("Could not read {}: {}", key, err);
+        storage.write(&format!("{}.bak", key), text.as_bytes()).ok();
+        T::default()
+    }))
+}
+
+pub fn platform_storage() -> Box<dyn Storage> {
+    #[cfg(target_arch = "wasm32")]
+    return Box::new(LocalStorage);
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(FileStorage);
+}
+
+// Keys are file paths relative to the working directory
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(key)
+    }
+
+    // Write to a temporary file first so a crash never leaves a half written file
+    fn write(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        use std::io::Write;
+
+        let temp_file = format!("{}.tmp", key);
+        let mut file = std::fs::File::create(&temp_file)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(temp_file, key)
+    }
+}
+
+// Talks to the `agical_storage` plugin in storage.js, which keeps values
+// base64 encoded in localStorage
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn agical_storage_len(key: *const u8, key_len: usize) -> i32;
+    fn agical_storage_read(key: *const u8, key_len: usize, dest: *mut u8, dest_len: usize);
+    fn agical_storage_write(key: *const u8, key_len: usize, data: *const u8, data_len: usize) -> i32;
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        let len = unsafe { agical_storage_len(key.as_ptr(), key.len()) };
+        if len < 0 {
+            return Err(io::Error::new(io::ErrorKind::NotFound, key.to_string()));
+        }
+        let mut data = vec![0; len as usize];
+        unsafe { agical_storage_read(key.as_ptr(), key.len(), data.as_mut_ptr(), data.len()) };
+        Ok(data)
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        match unsafe { agical_storage_write(key.as_ptr(), key.len(), data.as_ptr(), data.len()) } {
+            0 => Ok(()),
+            _ => Err(io::Error::other(format!("localStorage refused {}", key))),
+        }
+    }
+}
+
+// Keeps everything in memory, for tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStorage {
+    pub values: std::cell::RefCell<std::collections::HashMap<String, Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.values
+            .borrow()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, key.to_string()))
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        self.values.borrow_mut().insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+}
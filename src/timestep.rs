@@ -0,0 +1,34 @@
+// Simulation ticks per second, independent of the display refresh rate
+pub const TICK_RATE: f32 = 120.0;
+pub const TICK: f32 = 1.0 / TICK_RATE;
+
+// Longest frame we try to catch up on, so a stall doesn't spiral into
+// hundreds of ticks in a single frame
+const MAX_FRAME_TIME: f32 = 0.25;
+
+#[derive(Default)]
+pub struct FixedTimestep {
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    // Add the frame's time and return how many ticks should be simulated
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let mut ticks = 0;
+        while self.accumulator >= TICK {
+            self.accumulator -= TICK;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    // How far we are between the last simulated tick and the next one
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / TICK
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
@@ -0,0 +1,176 @@
+use crate::canvas::{self, Canvas};
+use crate::input::Input;
+use macroquad::prelude::*;
+
+// How far a finger has to drag from where it went down for full tilt
+const STICK_RADIUS: f32 = 70.0;
+// Big enough to hit with a thumb without looking
+const BUTTON_RADIUS: f32 = 48.0;
+const PAUSE_SIZE: f32 = 56.0;
+const BACK_WIDTH: f32 = 120.0;
+// The part of the screen, from the left, where a finger grabs the joystick
+const STICK_AREA: f32 = 0.6;
+// Leaderboard name for players with only a touch screen or a controller to
+// type on
+pub const TOUCH_NAME: &str = "Player";
+
+// On-screen controls for phones, driving the first player. They show up on
+// the first touch, so desktops never see them.
+#[derive(Default)]
+pub struct TouchControls {
+    pub active: bool,
+    // The finger steering and where it went down
+    stick: Option<(u64, Vec2)>,
+    stick_position: Vec2,
+    fire: bool,
+    bomb: bool,
+    pause: bool,
+    // A finger went down this frame, which confirms on menu screens
+    pub tapped: bool,
+    // Where it went down, on the canvas
+    pub tap: Option<Vec2>,
+}
+
+impl TouchControls {
+    // Take in this frame's touches; call once per frame
+    pub fn update(&mut self, canvas: &Canvas) {
+        self.fire = false;
+        self.bomb = false;
+        self.pause = false;
+        self.tapped = false;
+        self.tap = None;
+        let (fire_button, bomb_button, pause_button) = layout();
+
+        for touch in touches() {
+            let position = canvas.to_logical(touch.position);
+            match touch.phase {
+                TouchPhase::Started => {
+                    self.active = true;
+                    self.tapped = true;
+                    self.tap.get_or_insert(position);
+                    if pause_button.contains(position) {
+                        self.pause = true;
+                    } else if self.stick.is_none()
+                        && position.x < canvas::WIDTH * STICK_AREA
+                        && !fire_button.contains(&position)
+                        && !bomb_button.contains(&position)
+                    {
+                        self.stick = Some((touch.id, position));
+                        self.stick_position = position;
+                    }
+                }
+                TouchPhase::Moved | TouchPhase::Stationary => {
+                    if matches!(self.stick, Some((id, _)) if id == touch.id) {
+                        self.stick_position = position;
+                    }
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    if matches!(self.stick, Some((id, _)) if id == touch.id) {
+                        self.stick = None;
+                    }
+                    continue;
+                }
+            }
+            // Any finger resting on a button holds it down
+            if matches!(self.stick, Some((id, _)) if id == touch.id) {
+                continue;
+            }
+            self.fire |= fire_button.contains(&position);
+            self.bomb |= bomb_button.contains(&position);
+        }
+    }
+
+    // A finger went down on `button` this frame
+    pub fn tapped_on(&self, button: &Rect) -> bool {
+        self.tap.is_some_and(|tap| button.contains(tap))
+    }
+
+    pub fn input(&self) -> Input {
+        let mut input = Input {
+            fire: self.fire,
+            bomb: self.bomb,
+            pause: self.pause,
+            ..Default::default()
+        };
+        if let Some((_, origin)) = self.stick {
+            input.set_stick((self.stick_position - origin) / STICK_RADIUS);
+        }
+        input
+    }
+
+    // The joystick and buttons, over the playfield
+    pub fn draw(&self) {
+        if !self.active {
+            return;
+        }
+        let (fire_button, bomb_button, pause_button) = layout();
+        let faint = Color::new(1.0, 1.0, 1.0, 0.25);
+        let held = Color::new(1.0, 1.0, 1.0, 0.5);
+
+        if let Some((_, origin)) = self.stick {
+            let knob = origin + (self.stick_position - origin).clamp_length_max(STICK_RADIUS);
+            draw_circle_lines(origin.x, origin.y, STICK_RADIUS, 3.0, faint);
+            draw_circle(knob.x, knob.y, STICK_RADIUS / 3.0, held);
+        }
+
+        for (button, label, pressed) in [
+            (fire_button, "FIRE", self.fire),
+            (bomb_button, "BOMB", self.bomb),
+        ] {
+            draw_circle(button.x, button.y, button.r, if pressed { held } else { faint });
+            let label_dim = measure_text(label, None, 24, 1.0);
+            draw_text(
+                label,
+                button.x - label_dim.width / 2.0,
+                button.y + label_dim.offset_y / 2.0,
+                24.0,
+                WHITE,
+            );
+        }
+
+        draw_rectangle(pause_button.x, pause_button.y, pause_button.w, pause_button.h, faint);
+        let bar = pause_button.w / 6.0;
+        for x in [2.0, 4.0] {
+            draw_rectangle(
+                pause_button.x + bar * x - bar / 2.0,
+                pause_button.y + bar * 1.5,
+                bar,
+                pause_button.h - bar * 3.0,
+                WHITE,
+            );
+        }
+    }
+}
+
+// Fire and bomb under the right thumb, pause out of the way in the corner
+fn layout() -> (Circle, Circle, Rect) {
+    let (width, height) = (canvas::WIDTH, canvas::HEIGHT);
+    let fire = Circle::new(width - BUTTON_RADIUS * 1.5, height - BUTTON_RADIUS * 2.0, BUTTON_RADIUS);
+    let bomb = Circle::new(width - BUTTON_RADIUS * 3.5, height - BUTTON_RADIUS * 1.2, BUTTON_RADIUS);
+    let pause = Rect::new(width - PAUSE_SIZE - 16.0, 16.0, PAUSE_SIZE, PAUSE_SIZE);
+    (fire, bomb, pause)
+}
+
+// The way out of the menu screens, in the corner the titles leave free
+pub fn back_button() -> Rect {
+    Rect::new(16.0, 16.0, BACK_WIDTH, PAUSE_SIZE)
+}
+
+// A thumb-sized box with a label, for menu screens
+pub fn draw_button(button: Rect, label: &str, selected: bool) {
+    let fill = if selected {
+        Color::new(1.0, 1.0, 1.0, 0.35)
+    } else {
+        Color::new(1.0, 1.0, 1.0, 0.15)
+    };
+    draw_rectangle(button.x, button.y, button.w, button.h, fill);
+    draw_rectangle_lines(button.x, button.y, button.w, button.h, 2.0, Color::new(1.0, 1.0, 1.0, 0.4));
+    let label_dim = measure_text(label, None, 24, 1.0);
+    draw_text(
+        label,
+        button.x + (button.w - label_dim.width) / 2.0,
+        button.y + (button.h + label_dim.offset_y) / 2.0,
+        24.0,
+        WHITE,
+    );
+}
@@ -0,0 +1,209 @@
+use crate::powerup::Effects;
+use crate::projectile::{Projectile, ProjectileKind};
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+pub const WEAPON_FILE: &str = "weapon.ron";
+
+// Degrees between the extra bullets added by the spread power-up
+const SPREAD_ANGLE: f32 = 12.0;
+
+// The player's gun as described in assets/weapon.ron
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeaponDef {
+    pub name: String,
+    // Upgrade levels, from the starting gun up
+    pub tiers: Vec<Tier>,
+    pub charge: ChargeShot,
+    // Bombs at the start of a run, and how many can be carried
+    pub bombs: u32,
+    pub max_bombs: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tier {
+    // Seconds between shots while fire is held
+    pub cooldown: f32,
+    // Bullets per shot, fanned out `spread` degrees apart
+    pub shots: u32,
+    pub spread: f32,
+    pub speed: f32,
+    pub damage: u32,
+    pub projectile: ProjectileKind,
+}
+
+// Released after holding fire for `time` seconds
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChargeShot {
+    pub time: f32,
+    pub damage: u32,
+    pub size: f32,
+    pub speed: f32,
+    pub projectile: ProjectileKind,
+}
+
+impl WeaponDef {
+    pub fn parse(text: &str) -> Result<WeaponDef, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fired {
+    Shot,
+    Charged,
+}
+
+// The state of the player's gun during a run
+pub struct Weapon {
+    pub tier: usize,
+    // Seconds fire has been held
+    pub charge: f32,
+    pub bombs: u32,
+    cooldown: f32,
+    was_firing: bool,
+}
+
+impl Weapon {
+    pub fn new(def: &WeaponDef) -> Self {
+        Weapon {
+            tier: 0,
+            charge: 0.0,
+            bombs: def.bombs,
+            cooldown: 0.0,
+            was_firing: false,
+        }
+    }
+
+    // Returns false if the gun is already at its top tier
+    pub fn upgrade(&mut self, def: &WeaponDef) -> bool {
+        if self.tier + 1 < def.tiers.len() {
+            self.tier += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn add_bomb(&mut self, def: &WeaponDef) {
+        self.bombs = (self.bombs + 1).min(def.max_bombs);
+    }
+
+    // How far the charged shot has built up, from 0 to 1
+    pub fn charge_fraction(&self, def: &WeaponDef) -> f32 {
+        (self.charge / def.charge.time.max(f32::EPSILON)).min(1.0)
+    }
+
+    // Fire while `fire` is held, and let the charged shot go when it is
+    // released. Bullets leave from `origin`.
+    pub fn update(
+        &mut self,
+        dt: f32,
+        def: &WeaponDef,
+        effects: &Effects,
+        fire: bool,
+        origin: Vec2,
+        out: &mut Vec<Projectile>,
+    ) -> Option<Fired> {
+        self.cooldown -= dt;
+        let was_firing = std::mem::replace(&mut self.was_firing, fire);
+
+        if !fire {
+            let charged = was_firing && self.charge >= def.charge.time;
+            self.charge = 0.0;
+            if !charged {
+                return None;
+            }
+            let charge = &def.charge;
+            let mut bullet = Projectile::new(origin, vec2(0.0, -charge.speed), charge.projectile, YELLOW);
+            bullet.shape.size = charge.size;
+            bullet.damage = charge.damage;
+            out.push(bullet);
+            return Some(Fired::Charged);
+        }
+
+        self.charge += dt;
+        if self.cooldown > 0.0 {
+            return None;
+        }
+        let tier = &def.tiers[self.tier.min(def.tiers.len() - 1)];
+        self.cooldown = if effects.rapid_fire > 0.0 {
+            tier.cooldown / 2.0
+        } else {
+            tier.cooldown
+        };
+
+        // The spread power-up adds a bullet on either side of the fan
+        let (shots, spread) = if effects.spread > 0.0 {
+            (tier.shots + 2, tier.spread.max(SPREAD_ANGLE))
+        } else {
+            (tier.shots, tier.spread)
+        };
+        let start = -spread * (shots as f32 - 1.0) / 2.0;
+        for shot in 0..shots {
+            let angle = (start + spread * shot as f32).to_radians();
+            let velocity = Vec2::from_angle(angle).rotate(vec2(0.0, -tier.speed));
+            let mut bullet = Projectile::new(origin, velocity, tier.projectile, WHITE);
+            bullet.shape.size = 32.0;
+            bullet.damage = tier.damage;
+            out.push(bullet);
+        }
+        Some(Fired::Shot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def() -> WeaponDef {
+        WeaponDef::parse(include_str!("../assets/weapon.ron")).unwrap()
+    }
+
+    // Hold or release fire for `seconds`, collecting what the gun did
+    fn hold(weapon: &mut Weapon, def: &WeaponDef, effects: &Effects, fire: bool, seconds: f32) -> Vec<Fired> {
+        let dt = 1.0 / 120.0;
+        let mut out = vec![];
+        (0..(seconds / dt).round() as usize)
+            .filter_map(|_| weapon.update(dt, def, effects, fire, Vec2::ZERO, &mut out))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_shooting_while_charging() {
+        let def = def();
+        let mut weapon = Weapon::new(&def);
+        let seconds = def.charge.time + 1.0;
+        let fired = hold(&mut weapon, &def, &Effects::default(), true, seconds);
+        let expected = (seconds / def.tiers[0].cooldown).ceil() as usize;
+        assert_eq!(fired.len(), expected);
+        assert!(fired.iter().all(|fired| *fired == Fired::Shot));
+        assert_eq!(weapon.charge_fraction(&def), 1.0);
+
+        let released = hold(&mut weapon, &def, &Effects::default(), false, 0.1);
+        assert_eq!(released, vec![Fired::Charged]);
+        assert_eq!(weapon.charge, 0.0);
+    }
+
+    #[test]
+    fn rapid_fire_keeps_working_while_held() {
+        let def = def();
+        let effects = Effects {
+            rapid_fire: 10.0,
+            ..Default::default()
+        };
+        let seconds = def.charge.time + 1.0;
+        let normal = hold(&mut Weapon::new(&def), &def, &Effects::default(), true, seconds);
+        let rapid = hold(&mut Weapon::new(&def), &def, &effects, true, seconds);
+        // Twice as often, give or take a tick of rounding
+        assert!(rapid.len() >= normal.len() * 2 - 1);
+    }
+
+    #[test]
+    fn early_release_fires_nothing_extra() {
+        let def = def();
+        let mut weapon = Weapon::new(&def);
+        hold(&mut weapon, &def, &Effects::default(), true, def.charge.time / 2.0);
+        assert!(hold(&mut weapon, &def, &Effects::default(), false, 0.1).is_empty());
+    }
+}
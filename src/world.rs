@@ -0,0 +1,937 @@
+use crate::boss::{Boss, BossEvent};
+use crate::combo::Combo;
+use crate::data::GameData;
+use crate::difficulty::{Difficulty, Director};
+use crate::enemy::{Enemy, FirePattern};
+use crate::level::{Sequencer, SpawnOrder, StageEvent};
+use crate::pattern::PatternRunner;
+use crate::player::{self, Player, MAX_PLAYERS};
+use crate::powerup::{PowerUp, PowerUpKind};
+use crate::input::Input;
+use crate::projectile::Projectile;
+use crate::rng::Rng;
+use crate::shape::Shape;
+use crate::weapon::Fired;
+use macroquad::prelude::*;
+use std::rc::Rc;
+
+// How close a shot has to pass the ship's center to count as a graze
+const GRAZE_RADIUS: f32 = 28.0;
+const GRAZE_SCORE: u32 = 10;
+// Score for an upgrade picked up with the gun at its top tier
+const MAXED_UPGRADE_SCORE: u32 = 500;
+// Damage a bomb deals to every part of a boss
+const BOMB_DAMAGE: u32 = 10;
+// Seconds the ship can't be hurt after its shield breaks
+const SHIELD_GRACE: f32 = 1.0;
+// Seconds between losing a ship and the next one flying in
+const RESPAWN_DELAY: f32 = 1.0;
+// Seconds to accept a continue once the last ship is lost
+pub const CONTINUE_TIME: f32 = 10.0;
+// How close a partner has to stay to a wreck, and for how long, to revive it
+const REVIVE_RADIUS: f32 = 48.0;
+pub const REVIVE_TIME: f32 = 2.0;
+const HOSTILE_COLOR: u32 = 0xff7a6e;
+
+const ENEMY_COLORS: [u32; 7] = [
+    0xfca78b, 0xf6957d, 0xf0826e, 0xea7060, 0xe45d51, 0xde4b43, 0xd83834,
+];
+
+// Things that happened during a step which the renderer/audio may react to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldEvent {
+    Shot,
+    ChargedShot,
+    EnemyShot,
+    // A bullet damaged an enemy without destroying it
+    Hit { x: f32, y: f32 },
+    Explosion { x: f32, y: f32, size: f32 },
+    Stage(StageEvent),
+    // The boss entered a new phase, 0 once its intro is over
+    BossPhase(usize),
+    BossDefeated,
+    PowerUp(PowerUpKind),
+    // A bomb went off at a ship and cleared the screen
+    Bomb { x: f32, y: f32 },
+    ShieldBroken { x: f32, y: f32 },
+    // Points were scored at a spot, after every multiplier
+    Scored { x: f32, y: f32, points: u32 },
+    // A ship was destroyed, with lives to spare, a partner to revive it or a
+    // continue on offer
+    PlayerDied { x: f32, y: f32 },
+    Respawned,
+    // A downed player was brought back by their partner
+    Revived(usize),
+    Continued,
+    GameOver,
+}
+
+// The game simulation, free of any rendering or window access
+pub struct World {
+    pub data: Rc<GameData>,
+    pub width: f32,
+    pub height: f32,
+    pub enemies: Vec<Enemy>,
+    pub bullets: Vec<Projectile>,
+    pub hostile_bullets: Vec<Projectile>,
+    pub boss: Option<Boss>,
+    pub powerups: Vec<PowerUp>,
+    // One per player, controlled by the input at the same index
+    pub players: Vec<Player>,
+    // Index into the difficulty presets
+    pub difficulty: usize,
+    // Credits shared by the whole team
+    pub continues: u32,
+    // Seconds left to take a continue, while the offer is up
+    pub continue_timer: Option<f32>,
+    // Fire has to be let go before it counts as taking the continue
+    continue_armed: bool,
+    pub director: Director,
+    pub combo: Combo,
+    // Team score, the sum of every player's
+    pub score: u32,
+    pub high_score: u32,
+    pub tick: u32,
+    pub time: f64,
+    rng: Rng,
+    sequencer: Sequencer,
+}
+
+impl World {
+    pub fn new(
+        data: Rc<GameData>,
+        width: f32,
+        height: f32,
+        high_score: u32,
+        seed: u64,
+        difficulty: usize,
+        players: usize,
+    ) -> Self {
+        let preset = &data.difficulties[difficulty];
+        let players = players.clamp(1, MAX_PLAYERS);
+        World {
+            width,
+            height,
+            enemies: vec![],
+            bullets: vec![],
+            hostile_bullets: vec![],
+            boss: None,
+            powerups: vec![],
+            players: (0..players)
+                .map(|index| Player::new(index, players, &data, preset, width, height))
+                .collect(),
+            difficulty,
+            continues: preset.continues,
+            continue_timer: None,
+            continue_armed: false,
+            director: Director::new(preset),
+            combo: Combo::default(),
+            score: 0,
+            high_score,
+            tick: 0,
+            time: 0.0,
+            rng: Rng::new(seed),
+            sequencer: Sequencer::new(&data),
+            data,
+        }
+    }
+
+    // Start a fresh run from `seed` for `players` ships, keeping the high score
+    // and difficulty
+    pub fn reset(&mut self, seed: u64, players: usize) {
+        *self = World::new(
+            self.data.clone(),
+            self.width,
+            self.height,
+            self.high_score,
+            seed,
+            self.difficulty,
+            players,
+        );
+    }
+
+    pub fn preset(&self) -> &Difficulty {
+        &self.data.difficulties[self.difficulty]
+    }
+
+    fn spawn_enemy(&mut self, order: SpawnOrder) {
+        let archetype = &self.data.enemies[order.kind];
+        let size = self.rng.gen_range(archetype.size.0, archetype.size.1);
+        let x = match order.x {
+            Some(x) => clamp(x, size / 2.0, self.width - size / 2.0),
+            None => self.rng.gen_range(size / 2.0, self.width - size / 2.0),
+        };
+        let y = -size + order.y_offset;
+        let speed = match order.speed {
+            Some(speed) => speed,
+            None => self.rng.gen_range(archetype.speed.0, archetype.speed.1),
+        } * self.director.speed;
+
+        self.enemies.push(Enemy {
+            shape: Shape {
+                size,
+                speed,
+                x,
+                y,
+                prev_x: x,
+                prev_y: y,
+                color: match self.rng.choose(&ENEMY_COLORS) {
+                    Some(choice) => Color::from_hex(*choice),
+                    None => Color::from_hex(0x000000),
+                },
+                collided: false,
+            },
+            kind: order.kind,
+            health: archetype.hit_points,
+            flash: 0.0,
+            fire_cooldown: archetype
+                .fire
+                .as_ref()
+                .map_or(0.0, |fire| self.rng.gen_range(fire.cooldown.0, fire.cooldown.1)),
+            pattern: None,
+            path: order.path,
+            origin_x: x,
+            path_time: 0.0,
+            hover_time: 0.0,
+        })
+    }
+
+    // `inputs` holds one snapshot per player; missing ones count as idle
+    pub fn step(&mut self, dt: f32, inputs: &[Input]) -> Vec<WorldEvent> {
+        let mut events = vec![];
+        self.tick += 1;
+        self.time += dt as f64;
+
+        for player in &mut self.players {
+            player.ship.store_position();
+        }
+        for enemy in &mut self.enemies {
+            enemy.shape.store_position();
+        }
+        for bullet in &mut self.bullets {
+            bullet.shape.store_position();
+        }
+        for bullet in &mut self.hostile_bullets {
+            bullet.shape.store_position();
+        }
+        if let Some(boss) = &mut self.boss {
+            boss.shape.store_position();
+        }
+        for powerup in &mut self.powerups {
+            powerup.shape.store_position();
+        }
+        // Everything holds still while a continue is on offer, which either
+        // player can take
+        if let Some(timer) = &mut self.continue_timer {
+            *timer -= dt;
+            let fire = inputs.iter().take(self.players.len()).any(|input| input.fire);
+            if !fire {
+                self.continue_armed = true;
+            } else if self.continue_armed {
+                self.continue_timer = None;
+                self.continues -= 1;
+                let lives = self.data.difficulties[self.difficulty].lives;
+                for player in &mut self.players {
+                    player.lives = lives;
+                    player.down = false;
+                    player.revive = 0.0;
+                    player.respawn = RESPAWN_DELAY;
+                }
+                events.push(WorldEvent::Continued);
+                return events;
+            }
+            if *timer <= 0.0 {
+                events.push(WorldEvent::GameOver);
+            }
+            return events;
+        }
+
+        let preset = &self.data.difficulties[self.difficulty];
+        for player in &mut self.players {
+            player.update(dt, preset);
+        }
+        self.combo.update(dt);
+
+        // --- Players ---
+        let idle = Input::default();
+        let count = self.players.len();
+        let mut bombs = vec![];
+        for (index, player) in self.players.iter_mut().enumerate() {
+            // Bring in the next ship, or ignore the controls while there is none
+            if player.respawn > 0.0 {
+                player.respawn -= dt;
+                if player.respawn <= 0.0 {
+                    player.respawn_at(
+                        player::spawn_x(index, count, self.width),
+                        self.height * 0.8,
+                        preset.invulnerability,
+                    );
+                    events.push(WorldEvent::Respawned);
+                }
+            }
+            let input = match inputs.get(index) {
+                Some(input) if player.in_play() => input,
+                _ => &idle,
+            };
+
+            player.steer(input, dt, self.width, self.height);
+
+            // Did player shoot?
+            let first = self.bullets.len();
+            let fired = player.weapon.update(
+                dt,
+                &self.data.weapon,
+                &player.effects,
+                input.fire,
+                vec2(player.ship.x, player.ship.y - 24.0),
+                &mut self.bullets,
+            );
+            for bullet in &mut self.bullets[first..] {
+                bullet.owner = index;
+            }
+            match fired {
+                Some(Fired::Shot) => events.push(WorldEvent::Shot),
+                Some(Fired::Charged) => events.push(WorldEvent::ChargedShot),
+                None => {}
+            }
+
+            // Secondary fire
+            if player.take_bomb(input.bomb) {
+                bombs.push(index);
+            }
+        }
+        for index in bombs {
+            self.detonate_bomb(index, &mut events);
+        }
+
+        // Flying over a downed partner's wreck brings them back
+        let ships: Vec<Vec2> = self
+            .players
+            .iter()
+            .filter(|player| player.in_play())
+            .map(Player::position)
+            .collect();
+        let invulnerability = self.preset().invulnerability;
+        for (index, player) in self.players.iter_mut().enumerate() {
+            if !player.down {
+                continue;
+            }
+            let wreck = player.position();
+            if ships.iter().any(|ship| ship.distance(wreck) < REVIVE_RADIUS) {
+                player.revive += dt;
+            } else {
+                player.revive = (player.revive - dt).max(0.0);
+            }
+            if player.revive >= REVIVE_TIME {
+                player.revived(invulnerability);
+                events.push(WorldEvent::Revived(index));
+            }
+        }
+
+        // --- Enemies ---
+        self.director
+            .update(&self.data.difficulties[self.difficulty], self.time, self.score);
+
+        // Let the stage (or the endless spawner) bring in new enemies
+        let stage_event = self.sequencer.update(
+            dt,
+            &self.data,
+            self.width,
+            self.enemies.is_empty() && self.boss.is_none(),
+            &self.director,
+            &mut self.rng,
+        );
+        if let Some(stage_event) = stage_event {
+            if let StageEvent::BossIncoming(kind) = stage_event {
+                self.boss = Some(Boss::new(kind, &self.data, self.width));
+            }
+            events.push(WorldEvent::Stage(stage_event));
+        }
+        for order in self.sequencer.take_spawns() {
+            self.spawn_enemy(order);
+        }
+
+        // Move the enemies
+        for enemy in &mut self.enemies {
+            enemy.advance(dt, self.width, self.height);
+        }
+
+        // Enemies on screen fire at the closest ship
+        let fallback = self.players[0].position();
+        for enemy in &mut self.enemies {
+            let Some(fire) = &self.data.enemies[enemy.kind].fire else {
+                continue;
+            };
+            let origin = vec2(enemy.shape.x, enemy.shape.y + enemy.shape.size / 2.0);
+            let target = player::nearest(&ships, origin).unwrap_or(fallback);
+
+            // Scripted patterns play out over many ticks before the cooldown starts
+            if let Some(runner) = &mut enemy.pattern {
+                if runner.update(dt, origin, target, &mut self.hostile_bullets) > 0 {
+                    events.push(WorldEvent::EnemyShot);
+                }
+                if runner.is_finished() {
+                    enemy.pattern = None;
+                }
+                continue;
+            }
+
+            enemy.fire_cooldown -= dt;
+            if enemy.fire_cooldown > 0.0 || enemy.shape.y < 0.0 {
+                continue;
+            }
+            enemy.fire_cooldown = self.rng.gen_range(fire.cooldown.0, fire.cooldown.1);
+
+            if let FirePattern::Script(name) = &fire.pattern {
+                if let Some(pattern) = self.data.patterns.get(name) {
+                    enemy.pattern = Some(PatternRunner::new(pattern.clone()));
+                }
+                continue;
+            }
+            for velocity in fire.pattern.velocities(origin, target, fire.speed) {
+                self.hostile_bullets.push(Projectile::new(
+                    origin,
+                    velocity,
+                    fire.projectile,
+                    Color::from_hex(HOSTILE_COLOR),
+                ));
+            }
+            events.push(WorldEvent::EnemyShot);
+        }
+
+        // The boss moves and fires on its own schedule
+        if let Some(boss) = &mut self.boss {
+            let target = player::nearest(&ships, vec2(boss.shape.x, boss.shape.y)).unwrap_or(fallback);
+            let boss_events = boss.update(
+                dt,
+                &self.data,
+                self.width,
+                self.height,
+                target,
+                &mut self.hostile_bullets,
+            );
+            // Only damage scores, so nobody in particular is credited here
+            for event in boss_events {
+                self.handle_boss_event(event, 0, &mut events);
+            }
+        }
+
+        // Move the bullets
+        for bullet in &mut self.bullets {
+            bullet.update(dt);
+        }
+        for bullet in &mut self.hostile_bullets {
+            bullet.update(dt);
+        }
+
+        // Collect power-ups touching a ship, first player first
+        for powerup in &mut self.powerups {
+            powerup.update(dt);
+        }
+        let height = self.height;
+        let players = &self.players;
+        let mut collected = vec![];
+        self.powerups.retain(|powerup| {
+            let rect = powerup.shape.rect();
+            let picked = players
+                .iter()
+                .position(|player| player.in_play() && player.ship.rect().overlaps(&rect));
+            if let Some(index) = picked {
+                collected.push((index, powerup.kind));
+                return false;
+            }
+            powerup.shape.y < height + powerup.shape.size
+        });
+        for (index, kind) in collected {
+            let player = &mut self.players[index];
+            match kind {
+                PowerUpKind::Bomb => player.weapon.add_bomb(&self.data.weapon),
+                PowerUpKind::Upgrade => {
+                    // Points instead once the gun is maxed out
+                    if !player.weapon.upgrade(&self.data.weapon) {
+                        let (x, y) = (player.ship.x, player.ship.y);
+                        self.award_at(index, MAXED_UPGRADE_SCORE, x, y, &mut events);
+                    }
+                }
+                _ => player.effects.apply(kind),
+            }
+            events.push(WorldEvent::PowerUp(kind));
+        }
+
+        for index in 0..self.players.len() {
+            let player = &self.players[index];
+            if !player.in_play() {
+                continue;
+            }
+
+            // Check for collision (Lose state)
+            let ship_rect = player.ship.rect();
+            let data = &self.data;
+            let mut hurt = self.enemies.iter().any(|enemy| {
+                !enemy.shape.collided && enemy.hitbox(&data.enemies[enemy.kind]).overlaps(&ship_rect)
+            });
+            if let Some(boss) = &self.boss {
+                hurt |= boss.part_at(data, &ship_rect).is_some();
+            }
+
+            // Check for enemy shots hitting the ship
+            let core = player.core();
+            hurt |= self
+                .hostile_bullets
+                .iter()
+                .any(|bullet| bullet.hitbox().overlaps(&core));
+
+            if hurt && player.invulnerable <= 0.0 {
+                let player = &mut self.players[index];
+                if player.effects.shield > 0.0 {
+                    // The shield soaks up the hit and buys a moment to get clear
+                    player.effects.shield = 0.0;
+                    player.invulnerable = SHIELD_GRACE;
+                    self.hostile_bullets
+                        .retain(|bullet| !bullet.hitbox().overlaps(&core));
+                    events.push(WorldEvent::ShieldBroken {
+                        x: player.ship.x,
+                        y: player.ship.y,
+                    });
+                } else {
+                    self.lose_ship(index, &mut events);
+                    continue;
+                }
+            }
+
+            // Shots that only just miss fill the graze meter
+            let ship = self.players[index].position();
+            let mut grazes = 0;
+            for bullet in &mut self.hostile_bullets {
+                let position = vec2(bullet.shape.x, bullet.shape.y);
+                if !bullet.grazed && position.distance(ship) < GRAZE_RADIUS {
+                    bullet.grazed = true;
+                    grazes += 1;
+                }
+            }
+            for _ in 0..grazes {
+                self.combo.graze();
+                self.award(index, GRAZE_SCORE);
+            }
+        }
+
+        // Check for bullet-enemy collisions, each bullet hitting at most one enemy
+        let mut boss_events = vec![];
+        let mut kills = vec![];
+        for bullet in self.bullets.iter_mut() {
+            let bullet_rect = bullet.shape.rect();
+            let data = &self.data;
+            let Some(enemy) = self.enemies.iter_mut().find(|enemy| {
+                !enemy.shape.collided
+                    && enemy.hitbox(&data.enemies[enemy.kind]).overlaps(&bullet_rect)
+            }) else {
+                // Bullets that miss every enemy may still hit the boss
+                let Some(boss) = &mut self.boss else {
+                    continue;
+                };
+                let Some(part) = boss.part_at(data, &bullet_rect) else {
+                    continue;
+                };
+                bullet.shape.collided = true;
+                if boss.is_vulnerable() {
+                    events.push(WorldEvent::Hit {
+                        x: bullet.shape.x,
+                        y: bullet.shape.y - bullet.shape.size / 2.0,
+                    });
+                }
+                let owner = bullet.owner;
+                boss_events.extend(
+                    boss.damage(data, part, bullet.damage)
+                        .into_iter()
+                        .map(|event| (owner, event)),
+                );
+                continue;
+            };
+
+            bullet.shape.collided = true;
+            if enemy.take_damage(bullet.damage) {
+                kills.push((bullet.owner, enemy.kind, enemy.shape.x, enemy.shape.y, enemy.shape.size));
+            } else {
+                events.push(WorldEvent::Hit {
+                    x: bullet.shape.x,
+                    y: bullet.shape.y - bullet.shape.size / 2.0,
+                });
+            }
+        }
+
+        for (owner, kind, x, y, size) in kills {
+            self.enemy_destroyed(owner, kind, x, y, size, &mut events);
+        }
+        for (owner, event) in boss_events {
+            self.handle_boss_event(event, owner, &mut events);
+        }
+
+        // Keep enemies that's on-screen
+        self.enemies
+            .retain(|enemy| enemy.shape.y < height + enemy.shape.size);
+
+        // Keep bullets that's on-screen
+        let width = self.width;
+        self.bullets
+            .retain(|bullet| bullet.is_on_screen(width, height));
+        self.hostile_bullets
+            .retain(|bullet| bullet.is_on_screen(width, height));
+
+        // Retain active entities
+        self.enemies.retain(|enemy| !enemy.shape.collided);
+        self.bullets.retain(|bullet| !bullet.shape.collided);
+
+        events
+    }
+
+    // Turn what the boss reports into score for `player` and world events
+    fn handle_boss_event(&mut self, event: BossEvent, player: usize, events: &mut Vec<WorldEvent>) {
+        match event {
+            BossEvent::Shot => events.push(WorldEvent::EnemyShot),
+            BossEvent::PhaseChanged(phase) => events.push(WorldEvent::BossPhase(phase)),
+            BossEvent::PartDestroyed { x, y, size, score } => {
+                self.combo.kill();
+                self.award_at(player, score, x, y, events);
+                events.push(WorldEvent::Explosion { x, y, size });
+                // Every part shot off leaves something behind
+                let kind = PowerUpKind::choose(&mut self.rng);
+                self.powerups.push(PowerUp::new(x, y, kind));
+            }
+            BossEvent::Defeated { score } => {
+                let (x, y) = self
+                    .boss
+                    .as_ref()
+                    .map_or((self.width / 2.0, 0.0), |boss| (boss.shape.x, boss.shape.y));
+                self.award_at(player, score, x, y, events);
+                // Clear the screen so the player can enjoy the outro
+                self.hostile_bullets.clear();
+                events.push(WorldEvent::BossDefeated);
+            }
+            BossEvent::Explosion { x, y, size } => events.push(WorldEvent::Explosion { x, y, size }),
+            BossEvent::Gone => self.boss = None,
+        }
+    }
+
+    // Add `points` scaled by the player's power-ups and the team combo,
+    // returning what was actually scored
+    fn award(&mut self, player: usize, points: u32) -> u32 {
+        let player = &mut self.players[player];
+        let points = points * player.effects.score_multiplier() * self.combo.multiplier();
+        player.score += points;
+        self.score += points;
+        self.high_score = self.high_score.max(self.score);
+        points
+    }
+
+    // Score with a popup at `x`, `y`
+    fn award_at(&mut self, player: usize, points: u32, x: f32, y: f32, events: &mut Vec<WorldEvent>) {
+        let points = self.award(player, points);
+        events.push(WorldEvent::Scored { x, y, points });
+    }
+
+    fn enemy_destroyed(&mut self, player: usize, kind: usize, x: f32, y: f32, size: f32, events: &mut Vec<WorldEvent>) {
+        self.combo.kill();
+        let archetype = &self.data.enemies[kind];
+        let (score, drop_chance) = (archetype.score, archetype.drop_chance);
+        self.award_at(player, score, x, y, events);
+        events.push(WorldEvent::Explosion { x, y, size });
+        if self.rng.gen_range(0.0, 1.0) < drop_chance {
+            let kind = PowerUpKind::choose(&mut self.rng);
+            self.powerups.push(PowerUp::new(x, y, kind));
+        }
+    }
+
+    // Take away a ship and start respawning. Out of ships, the player waits for
+    // a revive while a partner is still flying; otherwise a continue is offered
+    // or the run ends.
+    fn lose_ship(&mut self, index: usize, events: &mut Vec<WorldEvent>) {
+        let player = &mut self.players[index];
+        player.lose_ship();
+        let (x, y) = (player.ship.x, player.ship.y);
+        self.combo.reset();
+        self.hostile_bullets.clear();
+
+        if player.lives > 0 {
+            player.respawn = RESPAWN_DELAY;
+        } else {
+            player.down = true;
+            player.revive = 0.0;
+            if self.players.iter().all(|player| player.down) {
+                if self.continues > 0 {
+                    self.continue_timer = Some(CONTINUE_TIME);
+                    self.continue_armed = false;
+                } else {
+                    events.push(WorldEvent::GameOver);
+                    return;
+                }
+            }
+        }
+        events.push(WorldEvent::PlayerDied { x, y });
+    }
+
+    // Destroy everything on screen and hurt every part of the boss
+    fn detonate_bomb(&mut self, player: usize, events: &mut Vec<WorldEvent>) {
+        self.hostile_bullets.clear();
+        let mut kills = vec![];
+        for enemy in &mut self.enemies {
+            if enemy.shape.collided {
+                continue;
+            }
+            enemy.shape.collided = true;
+            kills.push((enemy.kind, enemy.shape.x, enemy.shape.y, enemy.shape.size));
+        }
+        for (kind, x, y, size) in kills {
+            self.enemy_destroyed(player, kind, x, y, size, events);
+        }
+
+        let mut boss_events = vec![];
+        if let Some(boss) = &mut self.boss {
+            for part in 0..boss.parts.len() {
+                boss_events.extend(boss.damage(&self.data, part, BOMB_DAMAGE));
+            }
+        }
+        for event in boss_events {
+            self.handle_boss_event(event, player, events);
+        }
+        let ship = self.players[player].position();
+        events.push(WorldEvent::Bomb { x: ship.x, y: ship.y });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+    use crate::level::Path;
+    use crate::projectile::ProjectileKind;
+    use crate::timestep::TICK;
+
+    fn world(difficulty: &str, players: usize) -> World {
+        let data = data::bundled();
+        let difficulty = data.difficulty_index(difficulty).unwrap();
+        World::new(data, 800.0, 600.0, 0, 1, difficulty, players)
+    }
+
+    // Step `seconds` worth of ticks with the same input for everyone
+    fn run(world: &mut World, seconds: f32, input: Input) -> Vec<WorldEvent> {
+        let mut events = vec![];
+        for _ in 0..(seconds / TICK).round() as usize {
+            events.extend(world.step(TICK, &[input; MAX_PLAYERS]));
+        }
+        events
+    }
+
+    // A still enemy `above` pixels over the first ship
+    fn place_enemy(world: &mut World, name: &str, above: f32) {
+        let ship = world.players[0].position();
+        world.spawn_enemy(SpawnOrder {
+            kind: world.data.archetype_index(name).unwrap(),
+            x: Some(ship.x),
+            y_offset: 0.0,
+            speed: Some(0.0),
+            path: Path::Straight,
+        });
+        let enemy = world.enemies.last_mut().unwrap();
+        enemy.shape.y = ship.y - above;
+        enemy.shape.store_position();
+    }
+
+    // Put a stopped enemy shot on player `index`'s core and step once
+    fn shoot_down(world: &mut World, index: usize) -> Vec<WorldEvent> {
+        let position = world.players[index].position();
+        world
+            .hostile_bullets
+            .push(Projectile::new(position, Vec2::ZERO, ProjectileKind::Bullet, RED));
+        world.step(TICK, &[Input::default(); MAX_PLAYERS])
+    }
+
+    fn fire() -> Input {
+        Input {
+            fire: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_stage_spawns_enemies() {
+        let mut world = world("Normal", 1);
+        let events = run(&mut world, 3.0, Input::default());
+        assert!(events.contains(&WorldEvent::Stage(StageEvent::Started(0))));
+        assert!(!world.enemies.is_empty());
+    }
+
+    #[test]
+    fn shooting_an_enemy_scores() {
+        let mut world = world("Normal", 1);
+        place_enemy(&mut world, "small", 150.0);
+        let events = run(&mut world, 1.0, fire());
+
+        assert!(events.contains(&WorldEvent::Shot));
+        assert!(events.iter().any(|event| matches!(event, WorldEvent::Explosion { .. })));
+        let scored: u32 = events
+            .iter()
+            .map(|event| match event {
+                WorldEvent::Scored { points, .. } => *points,
+                _ => 0,
+            })
+            .sum();
+        assert!(scored >= world.data.enemies[0].score);
+        assert_eq!(world.score, scored);
+        assert_eq!(world.players[0].score, scored);
+        assert_eq!(world.high_score, scored);
+    }
+
+    #[test]
+    fn flying_into_an_enemy_loses_a_ship() {
+        let mut world = world("Normal", 1);
+        place_enemy(&mut world, "small", 0.0);
+        let lives = world.players[0].lives;
+        let events = world.step(TICK, &[Input::default()]);
+
+        assert!(events.iter().any(|event| matches!(event, WorldEvent::PlayerDied { .. })));
+        assert_eq!(world.players[0].lives, lives - 1);
+        assert!(!world.players[0].in_play());
+    }
+
+    #[test]
+    fn lost_ship_respawns_invulnerable() {
+        let mut world = world("Normal", 1);
+        let events = shoot_down(&mut world, 0);
+        assert!(events.iter().any(|event| matches!(event, WorldEvent::PlayerDied { .. })));
+        assert!(world.hostile_bullets.is_empty());
+
+        let events = run(&mut world, RESPAWN_DELAY + TICK, Input::default());
+        assert!(events.contains(&WorldEvent::Respawned));
+        assert!(world.players[0].in_play());
+        assert!(world.players[0].invulnerable > 0.0);
+
+        // Shots pass through while it blinks
+        let events = shoot_down(&mut world, 0);
+        assert!(!events.iter().any(|event| matches!(event, WorldEvent::PlayerDied { .. })));
+    }
+
+    #[test]
+    fn shield_soaks_up_a_hit() {
+        let mut world = world("Normal", 1);
+        world.players[0].effects.apply(PowerUpKind::Shield);
+        let lives = world.players[0].lives;
+        let events = shoot_down(&mut world, 0);
+
+        assert!(events.iter().any(|event| matches!(event, WorldEvent::ShieldBroken { .. })));
+        assert_eq!(world.players[0].lives, lives);
+        assert!(world.players[0].in_play());
+    }
+
+    #[test]
+    fn continue_needs_fire_released_first() {
+        let mut world = world("Normal", 1);
+        let continues = world.continues;
+        world.players[0].lives = 1;
+        shoot_down(&mut world, 0);
+        assert!(world.players[0].down);
+        assert_eq!(world.continue_timer, Some(CONTINUE_TIME));
+
+        // Fire still held from playing doesn't count
+        let events = run(&mut world, 0.5, fire());
+        assert!(events.is_empty());
+        assert!(world.continue_timer.is_some());
+
+        world.step(TICK, &[Input::default()]);
+        let events = world.step(TICK, &[fire()]);
+        assert_eq!(events, vec![WorldEvent::Continued]);
+        assert_eq!(world.continue_timer, None);
+        assert_eq!(world.continues, continues - 1);
+        assert_eq!(world.players[0].lives, world.preset().lives);
+        assert!(!world.players[0].down);
+
+        let events = run(&mut world, RESPAWN_DELAY + TICK, Input::default());
+        assert!(events.contains(&WorldEvent::Respawned));
+    }
+
+    #[test]
+    fn continue_offer_runs_out() {
+        let mut world = world("Normal", 1);
+        world.players[0].lives = 1;
+        shoot_down(&mut world, 0);
+        let events = run(&mut world, CONTINUE_TIME + TICK, Input::default());
+        assert!(events.contains(&WorldEvent::GameOver));
+    }
+
+    #[test]
+    fn last_ship_without_continues_ends_the_run() {
+        let mut world = world("Nightmare", 1);
+        assert_eq!(world.continues, 0);
+        world.players[0].lives = 1;
+        let events = shoot_down(&mut world, 0);
+        assert!(events.contains(&WorldEvent::GameOver));
+        assert_eq!(world.continue_timer, None);
+    }
+
+    #[test]
+    fn downed_partner_waits_for_a_revive() {
+        let mut world = world("Normal", 2);
+        world.players[1].lives = 1;
+        let events = shoot_down(&mut world, 1);
+        assert!(events.iter().any(|event| matches!(event, WorldEvent::PlayerDied { .. })));
+        assert!(world.players[1].down);
+        assert_eq!(world.continue_timer, None);
+
+        // Park the first ship on the wreck
+        let wreck = world.players[1].position();
+        world.players[0].ship.x = wreck.x;
+        world.players[0].ship.y = wreck.y;
+        let events = run(&mut world, REVIVE_TIME + TICK, Input::default());
+        assert!(events.contains(&WorldEvent::Revived(1)));
+        assert_eq!(world.players[1].lives, 1);
+    }
+
+    #[test]
+    fn bomb_clears_the_screen() {
+        let mut world = world("Normal", 1);
+        place_enemy(&mut world, "small", 200.0);
+        place_enemy(&mut world, "medium", 250.0);
+        world.hostile_bullets.push(Projectile::new(
+            vec2(100.0, 100.0),
+            Vec2::ZERO,
+            ProjectileKind::Bullet,
+            RED,
+        ));
+        let bombs = world.players[0].weapon.bombs;
+        assert!(bombs > 0);
+
+        let bomb = Input {
+            bomb: true,
+            ..Default::default()
+        };
+        let events = world.step(TICK, &[bomb]);
+        assert!(events.iter().any(|event| matches!(event, WorldEvent::Bomb { .. })));
+        let explosions = events
+            .iter()
+            .filter(|event| matches!(event, WorldEvent::Explosion { .. }))
+            .count();
+        assert_eq!(explosions, 2);
+        assert!(world.enemies.is_empty());
+        assert!(world.hostile_bullets.is_empty());
+        assert!(world.score > 0);
+        assert_eq!(world.players[0].weapon.bombs, bombs - 1);
+
+        // Holding the key drops just the one
+        let events = world.step(TICK, &[bomb]);
+        assert!(!events.iter().any(|event| matches!(event, WorldEvent::Bomb { .. })));
+        assert_eq!(world.players[0].weapon.bombs, bombs - 1);
+    }
+
+    #[test]
+    fn no_bombs_left_does_nothing() {
+        let mut world = world("Normal", 1);
+        world.players[0].weapon.bombs = 0;
+        place_enemy(&mut world, "small", 200.0);
+        let bomb = Input {
+            bomb: true,
+            ..Default::default()
+        };
+        let events = world.step(TICK, &[bomb]);
+        assert!(!events.iter().any(|event| matches!(event, WorldEvent::Bomb { .. })));
+        assert_eq!(world.enemies.len(), 1);
+    }
+}
@@ -0,0 +1,56 @@
+// miniquad plugin backing the game's `Storage` trait with localStorage.
+// Values are raw bytes on the Rust side, kept base64 encoded here.
+"use strict";
+
+(function () {
+    const PREFIX = "agical-tutorial/";
+
+    function read_string(ptr, len) {
+        return new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+    }
+
+    function load_bytes(key_ptr, key_len) {
+        const value = window.localStorage.getItem(PREFIX + read_string(key_ptr, key_len));
+        if (value === null) {
+            return null;
+        }
+        const binary = atob(value);
+        const bytes = new Uint8Array(binary.length);
+        for (let i = 0; i < binary.length; i++) {
+            bytes[i] = binary.charCodeAt(i);
+        }
+        return bytes;
+    }
+
+    function register_plugin(importObject) {
+        importObject.env.agical_storage_len = function (key_ptr, key_len) {
+            const bytes = load_bytes(key_ptr, key_len);
+            return bytes === null ? -1 : bytes.length;
+        };
+
+        importObject.env.agical_storage_read = function (key_ptr, key_len, dest_ptr, dest_len) {
+            const bytes = load_bytes(key_ptr, key_len);
+            if (bytes !== null) {
+                new Uint8Array(wasm_memory.buffer, dest_ptr, dest_len).set(bytes.subarray(0, dest_len));
+            }
+        };
+
+        importObject.env.agical_storage_write = function (key_ptr, key_len, data_ptr, data_len) {
+            const key = read_string(key_ptr, key_len);
+            const bytes = new Uint8Array(wasm_memory.buffer, data_ptr, data_len);
+            let binary = "";
+            for (let i = 0; i < bytes.length; i++) {
+                binary += String.fromCharCode(bytes[i]);
+            }
+            try {
+                window.localStorage.setItem(PREFIX + key, btoa(binary));
+                return 0;
+            } catch (e) {
+                console.error("Could not store " + key + ": " + e);
+                return 1;
+            }
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin, name: "agical_storage", version: "0.1.0" });
+})();